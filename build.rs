@@ -0,0 +1,32 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generates the crate's GL bindings.
+//!
+//! The bindings are a struct of function pointers rather than global functions, so that the
+//! crate doesn't depend on how, or whether, the application loaded its own.
+
+extern crate gl_generator;
+
+use gl_generator::{Api, Fallbacks, Profile, Registry, StructGenerator};
+use std::env;
+use std::fs::File;
+use std::path::PathBuf;
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let mut file = File::create(out_dir.join("gl_bindings.rs")).unwrap();
+
+    // Everything the crate calls under GL ES 3.0 and GL 2.1 also exists, with the same name, in
+    // GL 3.3 core.
+    Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, [])
+        .write_bindings(StructGenerator, &mut file)
+        .unwrap();
+}
@@ -1,20 +1,20 @@
 /* Any copyright is dedicated to the Public Domain.
  * http://creativecommons.org/publicdomain/zero/1.0/ */
 
-extern crate gl;
 extern crate glfw;
 extern crate image;
 extern crate lord_drawquaad;
 
-use gl::types::GLint;
 use glfw::{Action, Context, Key, OpenGlProfileHint, WindowEvent, WindowHint, WindowMode};
+use image::GenericImage;
+use lord_drawquaad::{DrawOptions, FitMode, Gl, Texture};
 use std::env;
 use std::os::raw::c_void;
 use std::process;
 
 pub fn main() {
     let path = env::args().nth(1).unwrap_or_else(|| usage());
-    let image = image::open(&path).unwrap().to_rgba();
+    let image = image::open(&path).unwrap();
 
     let mut glfw = glfw::init(glfw::LOG_ERRORS).unwrap();
     glfw.window_hint(WindowHint::ContextVersion(3, 3));
@@ -27,33 +27,22 @@ pub fn main() {
                                    .expect("Couldn't create a window!");
 
     window.make_current();
-    gl::load_with(|symbol| window.get_proc_address(symbol) as *const c_void);
+    window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
+    let gl = Gl::load_with(|symbol| window.get_proc_address(symbol) as *const c_void);
 
-    let context = lord_drawquaad::Context::new();
+    let context = lord_drawquaad::Context::new(&gl);
 
-    let mut texture = 0;
-    unsafe {
-        gl::GenTextures(1, &mut texture);
-        gl::BindTexture(gl::TEXTURE_RECTANGLE, texture);
+    let texture = Texture::from_image(&gl, &image);
 
-        gl::TexImage2D(gl::TEXTURE_RECTANGLE,
-                       0,
-                       gl::RGBA8 as GLint,
-                       image.width() as GLint,
-                       image.height() as GLint,
-                       0,
-                       gl::RGBA,
-                       gl::UNSIGNED_BYTE,
-                       (&*image).as_ptr() as *const c_void);
-
-        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
-        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
-        gl::TexParameteri(gl::TEXTURE_RECTANGLE, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
-    }
+    let options = DrawOptions {
+        fit: FitMode::Contain,
+        background: Some([0.0, 0.0, 0.0, 1.0]),
+        ..DrawOptions::default()
+    };
 
     while !window.should_close() {
-        context.draw(texture);
+        context.draw_texture_with_options(&texture, &options);
         window.swap_buffers();
 
         glfw.poll_events();
@@ -62,6 +51,11 @@ pub fn main() {
                 WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
                     window.set_should_close(true)
                 }
+                WindowEvent::FramebufferSize(width, height) => {
+                    unsafe {
+                        gl.Viewport(0, 0, width, height);
+                    }
+                }
                 _ => {}
             }
         }
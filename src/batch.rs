@@ -0,0 +1,169 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Drawing many quads at once.
+
+use gl::types::GLuint;
+use {IDENTITY_TRANSFORM, Rect, TextureTarget, Transform, Units};
+
+/// A list of quads to be drawn together with `Context::draw_batch()`.
+///
+/// Quads are sorted by texture before drawing, so that all quads sharing a texture are drawn
+/// with a single draw call. The sort is stable, so quads sharing a texture are drawn in the order
+/// they were added, but *the drawing order of quads with different textures is unspecified*. If
+/// quads with different textures overlap and the order matters, use one batch per layer.
+///
+/// Batches can be reused from frame to frame with `clear()` to avoid reallocating.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    target: Option<TextureTarget>,
+    quads: Vec<Quad>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Quad {
+    texture: GLuint,
+    src: Rect,
+    dest: Rect,
+    transform: Transform,
+}
+
+impl Batch {
+    /// Creates an empty batch of textures of the type that `Context::draw()` expects, which is
+    /// given by `Context::default_target()`.
+    pub fn new() -> Batch {
+        Batch {
+            target: None,
+            quads: vec![],
+        }
+    }
+
+    /// Creates an empty batch of textures of the given type.
+    ///
+    /// Every texture added to the batch must be of this type.
+    pub fn with_target(target: TextureTarget) -> Batch {
+        Batch {
+            target: Some(target),
+            quads: vec![],
+        }
+    }
+
+    /// Returns the type of the textures in this batch, or `None` if it was created with `new()`
+    /// and so holds textures of the context's default type.
+    pub fn target(&self) -> Option<TextureTarget> {
+        self.target
+    }
+
+    /// Adds a quad that draws the `src` rectangle of `texture` into the `dest` rectangle of the
+    /// viewport, with the same semantics as `Context::draw_region()`.
+    pub fn add(&mut self, texture: GLuint, src: Rect, dest: Rect) -> &mut Batch {
+        self.add_transformed(texture, src, dest, IDENTITY_TRANSFORM)
+    }
+
+    /// Adds a quad like `add()` does, then applies `transform` to it in clip space.
+    pub fn add_transformed(&mut self, texture: GLuint, src: Rect, dest: Rect, transform: Transform)
+                           -> &mut Batch {
+        self.quads.push(Quad {
+            texture: texture,
+            src: src,
+            dest: dest,
+            transform: transform,
+        });
+        self
+    }
+
+    /// Removes all quads from the batch, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.quads.clear()
+    }
+
+    /// Returns the number of quads in the batch.
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    /// Returns true if any destination rectangle is measured in pixels, in which case the
+    /// viewport size is needed to build the vertices.
+    pub(crate) fn needs_viewport_size(&self) -> bool {
+        self.quads.iter().any(|quad| quad.dest.units == Units::Pixels)
+    }
+
+    /// Returns true if the batch contains no quads.
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    /// Generates the vertices for the batch, grouped by texture, appending them to `vertices`.
+    ///
+    /// Returns the texture and vertex count of each group, in order. `viewport_size` is only
+    /// consulted for destination rectangles measured in pixels.
+    pub(crate) fn build(&self, vertices: &mut Vec<BatchVertex>, viewport_size: (f32, f32))
+                        -> Vec<(GLuint, usize)> {
+        let mut order: Vec<&Quad> = self.quads.iter().collect();
+        order.sort_by_key(|quad| quad.texture);
+
+        let mut groups: Vec<(GLuint, usize)> = vec![];
+        for quad in order {
+            let dest = quad.dest.to_normalized(|| viewport_size);
+
+            let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+            let mut quad_vertices = [BatchVertex::default(); 4];
+            for (vertex, &(u, v)) in quad_vertices.iter_mut().zip(corners.iter()) {
+                let x = (dest.x + dest.width * u) * 2.0 - 1.0;
+                let y = 1.0 - (dest.y + dest.height * v) * 2.0;
+                vertex.position = transform_point(&quad.transform, [x, y, 0.0, 1.0]);
+
+                let (s, t) = (quad.src.x + quad.src.width * u, quad.src.y + quad.src.height * v);
+                vertex.tex_coord = match quad.src.units {
+                    Units::Normalized => [s, t, 0.0, 0.0],
+                    Units::Pixels => [0.0, 0.0, s, t],
+                };
+            }
+
+            // Two triangles per quad, so that the whole batch is a single `GL_TRIANGLES` draw.
+            for &index in &[0, 1, 2, 2, 1, 3] {
+                vertices.push(quad_vertices[index]);
+            }
+
+            match groups.last_mut() {
+                Some(&mut (texture, ref mut count)) if texture == quad.texture => *count += 6,
+                _ => groups.push((quad.texture, 6)),
+            }
+        }
+        groups
+    }
+}
+
+impl Default for Batch {
+    fn default() -> Batch {
+        Batch::new()
+    }
+}
+
+/// A vertex in the dynamic vertex buffer used for batches.
+///
+/// Positions are in clip space. The texture coordinate is split in two: normalized coordinates
+/// in the first half and texel coordinates in the second, which the vertex shader sums. This lets
+/// quads with either kind of source rectangle share a draw call without knowing the size of the
+/// texture up front.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchVertex {
+    pub position: [f32; 4],
+    pub tex_coord: [f32; 4],
+}
+
+fn transform_point(transform: &Transform, point: [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for (row, result) in result.iter_mut().enumerate() {
+        *result = (0..4).map(|column| transform[column][row] * point[column]).sum();
+    }
+    result
+}
@@ -0,0 +1,246 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Detecting what the current GL context supports.
+
+use {Gl, TextureTarget};
+
+use gl;
+use gl::types::{GLenum, GLint, GLuint};
+use std::collections::HashSet;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// What the current GL context supports, as far as the crate is concerned.
+///
+/// `Context` detects these when it's created, uses them to pick its shaders, and exposes them
+/// through `Context::capabilities()`.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    /// The API the crate's shaders are written for, chosen from the version and profile.
+    pub api: Api,
+    /// Whether this is a core or compatibility profile of desktop GL, or GL ES.
+    pub profile: Profile,
+    /// The GL version, as a major and minor number.
+    pub version: (u32, u32),
+    /// The highest supported GLSL or GLSL ES version, as a major and minor number, such as
+    /// `(3, 30)`.
+    pub glsl_version: (u32, u32),
+    /// The largest width or height of a texture.
+    pub max_texture_size: u32,
+    /// The number of textures that can be bound at once across all shader stages, from
+    /// `GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS`. This is at least 2 in GL 2.1, 48 in GL 3.3, and 32
+    /// in GL ES 3.0.
+    pub max_texture_units: u32,
+    /// Whether `TextureTarget::Rectangle` textures are available.
+    pub rectangle_textures: bool,
+    /// Whether half- and single-precision floating-point textures, such as
+    /// `TextureFormat::Rgba16F`, are available.
+    pub float_textures: bool,
+    /// Whether `glDebugMessageCallback()` is available, through GL 4.3, GL ES 3.2,
+    /// `GL_KHR_debug`, or `GL_ARB_debug_output`.
+    pub debug_output: bool,
+    extensions: HashSet<String>,
+}
+
+impl Capabilities {
+    /// Queries the capabilities of the current GL context through the given functions.
+    ///
+    /// You must have a current valid GL context before calling this.
+    pub fn current(gl: &Gl) -> Capabilities {
+        unsafe {
+            let (es, version) = parse_gl_version(&get_string(gl, gl::VERSION));
+            let glsl_version = parse_version(&get_string(gl, gl::SHADING_LANGUAGE_VERSION));
+
+            let extensions = extensions(gl, version);
+            let has = |name: &str| extensions.contains(name);
+
+            let profile = if es {
+                Profile::Es
+            } else if version >= (3, 2) &&
+                    (get_integer(gl, gl::CONTEXT_PROFILE_MASK) as GLenum &
+                     gl::CONTEXT_CORE_PROFILE_BIT) != 0 {
+                Profile::Core
+            } else {
+                Profile::Compatibility
+            };
+            let api = choose_api(es, version, glsl_version);
+            let rectangle_textures = supports_rectangle_textures(es, version, has);
+            let float_textures = version >= (3, 0) || has("GL_ARB_texture_float");
+            let debug_output = if es {
+                version >= (3, 2) || has("GL_KHR_debug")
+            } else {
+                version >= (4, 3) || has("GL_KHR_debug") || has("GL_ARB_debug_output")
+            };
+
+            Capabilities {
+                api: api,
+                profile: profile,
+                version: version,
+                glsl_version: glsl_version,
+                max_texture_size: get_integer(gl, gl::MAX_TEXTURE_SIZE) as u32,
+                max_texture_units: get_integer(gl, gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS) as u32,
+                rectangle_textures: rectangle_textures,
+                float_textures: float_textures,
+                debug_output: debug_output,
+                extensions: extensions,
+            }
+        }
+    }
+
+    /// Returns true if the context advertises the given extension, such as
+    /// `"GL_EXT_texture_format_BGRA8888"`.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+}
+
+/// The flavor of OpenGL that a context draws with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Api {
+    /// Desktop OpenGL 3.3 or later, with GLSL 3.30 shaders.
+    Gl,
+    /// OpenGL ES 3.0 or later, or WebGL 2, with GLSL ES 3.00 shaders. There are no rectangle
+    /// textures in ES, so only `TextureTarget::Texture2D` textures can be drawn.
+    Gles,
+    /// Desktop OpenGL 2.1 through 3.2, with GLSL 1.20 shaders and vertex attributes set up on
+    /// every draw instead of in vertex array objects. Drawing rectangle textures requires
+    /// `GL_ARB_texture_rectangle`, and render targets require `GL_ARB_framebuffer_object`.
+    ///
+    /// This API is chosen for 3.2 core profiles too, but can't be drawn with there, so creating a
+    /// `Context` in one fails with `Error::UnsupportedApi`.
+    LegacyGl,
+}
+
+impl Api {
+    /// Detects the API of the current GL context from its version strings.
+    ///
+    /// You must have a current valid GL context before calling this.
+    pub fn current(gl: &Gl) -> Api {
+        Capabilities::current(gl).api
+    }
+
+    /// Picks the API for a context from its `GL_VERSION` and `GL_SHADING_LANGUAGE_VERSION`
+    /// strings, for contexts that aren't reached through a `Gl`, such as WebGL ones.
+    pub fn from_version_strings(version: &str, glsl_version: &str) -> Api {
+        let (es, version) = parse_gl_version(version);
+        choose_api(es, version, parse_version(glsl_version))
+    }
+}
+
+/// The kind of GL context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// A desktop GL core profile, without deprecated functionality.
+    Core,
+    /// A desktop GL compatibility profile, or any desktop context older than 3.2.
+    Compatibility,
+    /// OpenGL ES, or WebGL.
+    Es,
+}
+
+/// Returns the type of texture that the crate creates and draws by default in the current GL
+/// context: `TextureTarget::Rectangle` if the context has rectangle textures, and otherwise
+/// `TextureTarget::Texture2D`. This is much cheaper than detecting all the capabilities.
+pub unsafe fn default_target(gl: &Gl) -> TextureTarget {
+    let (es, version) = parse_gl_version(&get_string(gl, gl::VERSION));
+    // Rectangle textures are core from GL 3.1 on, so the extensions only matter before then.
+    let extensions = if es || version >= (3, 1) {
+        HashSet::new()
+    } else {
+        extensions(gl, version)
+    };
+    if supports_rectangle_textures(es, version, |name| extensions.contains(name)) {
+        TextureTarget::Rectangle
+    } else {
+        TextureTarget::Texture2D
+    }
+}
+
+/// Returns true if the current GL context is GL ES.
+pub unsafe fn is_es(gl: &Gl) -> bool {
+    parse_gl_version(&get_string(gl, gl::VERSION)).0
+}
+
+unsafe fn extensions(gl: &Gl, version: (u32, u32)) -> HashSet<String> {
+    // Core profiles from 3.1 on don't allow querying extensions all at once.
+    if version >= (3, 0) {
+        let count = get_integer(gl, gl::NUM_EXTENSIONS) as GLuint;
+        (0..count).filter_map(|index| {
+            let extension = gl.GetStringi(gl::EXTENSIONS, index);
+            if extension.is_null() {
+                return None
+            }
+            Some(CStr::from_ptr(extension as *const c_char).to_string_lossy().into_owned())
+        }).collect()
+    } else {
+        get_string(gl, gl::EXTENSIONS).split_whitespace()
+                                      .map(|name| name.to_owned())
+                                      .collect()
+    }
+}
+
+unsafe fn get_string(gl: &Gl, name: GLenum) -> String {
+    let string = gl.GetString(name);
+    if string.is_null() {
+        return String::new()
+    }
+    CStr::from_ptr(string as *const c_char).to_string_lossy().into_owned()
+}
+
+unsafe fn get_integer(gl: &Gl, name: GLenum) -> GLint {
+    let mut value = 0;
+    gl.GetIntegerv(name, &mut value);
+    value
+}
+
+/// Picks the API that the crate's shaders should be written for, given whether the context is
+/// GL ES and its GL and GLSL versions.
+pub fn choose_api(es: bool, version: (u32, u32), glsl_version: (u32, u32)) -> Api {
+    if es {
+        Api::Gles
+    } else if version < (3, 3) || glsl_version < (3, 30) {
+        Api::LegacyGl
+    } else {
+        Api::Gl
+    }
+}
+
+/// Returns true if a context with the given version and extensions has rectangle textures.
+pub fn supports_rectangle_textures<F>(es: bool, version: (u32, u32), has_extension: F) -> bool
+                                      where F: Fn(&str) -> bool {
+    !es && (version >= (3, 1) ||
+            has_extension("GL_ARB_texture_rectangle") ||
+            has_extension("GL_EXT_texture_rectangle") ||
+            has_extension("GL_NV_texture_rectangle"))
+}
+
+/// Parses a `GL_VERSION` string into whether the context is GL ES and its version. WebGL reports
+/// its own version, which is translated to that of the GL ES version it's based on.
+pub fn parse_gl_version(string: &str) -> (bool, (u32, u32)) {
+    if string.starts_with("WebGL") {
+        let (major, minor) = parse_version(string);
+        (true, (major + 1, minor))
+    } else {
+        (string.starts_with("OpenGL ES"), parse_version(string))
+    }
+}
+
+/// Finds the first `major.minor` version number in a version string, skipping prefixes such as
+/// `OpenGL ES GLSL ES`.
+pub fn parse_version(string: &str) -> (u32, u32) {
+    let start = match string.find(|c: char| c.is_digit(10)) {
+        Some(start) => start,
+        None => return (0, 0),
+    };
+    let mut numbers = string[start..].split(|c: char| !c.is_digit(10))
+                                     .map(|number| number.parse().unwrap_or(0));
+    (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+}
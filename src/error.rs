@@ -0,0 +1,124 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Errors that can occur while setting up and using a context.
+
+use Api;
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// An error that occurred while setting up or using a context.
+#[derive(Clone, Debug)]
+pub enum Error {
+    /// A shader failed to compile.
+    ShaderCompilation {
+        /// The stage of the shader that failed to compile.
+        stage: ShaderStage,
+        /// The info log reported by the driver.
+        log: String,
+        /// The line of GLSL source that the first error in the info log refers to, if the log
+        /// could be parsed.
+        source_line: Option<String>,
+    },
+    /// The shader program failed to link.
+    ProgramLink {
+        /// The info log reported by the driver.
+        log: String,
+    },
+    /// No active uniform with the given name exists in the context's shaders. Note that GLSL
+    /// compilers remove uniforms that don't contribute to the output.
+    UnknownUniform {
+        /// The name of the uniform.
+        name: String,
+    },
+    /// The uniform with the given name has a different type from the value assigned to it.
+    UniformTypeMismatch {
+        /// The name of the uniform.
+        name: String,
+    },
+    /// A render target's framebuffer isn't complete, usually because the driver can't render to
+    /// the requested texture format.
+    IncompleteFramebuffer {
+        /// The status reported by `glCheckFramebufferStatus()`.
+        status: u32,
+    },
+    /// The driver failed to create a GL object.
+    ObjectCreation {
+        /// The kind of object, such as `"vertex buffer"`.
+        object: &'static str,
+        /// The reason reported by the driver.
+        message: String,
+    },
+    /// The GL context uses an API that this backend can't draw with.
+    UnsupportedApi {
+        /// The API of the GL context.
+        api: Api,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::ShaderCompilation { stage, ref log, source_line: Some(ref source_line) } => {
+                write!(f,
+                       "{} shader compilation failed at `{}`: {}",
+                       stage,
+                       source_line.trim(),
+                       log.trim())
+            }
+            Error::ShaderCompilation { stage, ref log, source_line: None } => {
+                write!(f, "{} shader compilation failed: {}", stage, log.trim())
+            }
+            Error::ProgramLink { ref log } => write!(f, "program linking failed: {}", log.trim()),
+            Error::UnknownUniform { ref name } => write!(f, "no active uniform named `{}`", name),
+            Error::UniformTypeMismatch { ref name } => {
+                write!(f, "value doesn't match the type of uniform `{}`", name)
+            }
+            Error::IncompleteFramebuffer { status } => {
+                write!(f, "framebuffer is incomplete (status 0x{:04x})", status)
+            }
+            Error::ObjectCreation { object, ref message } => {
+                write!(f, "failed to create a {}: {}", object, message)
+            }
+            Error::UnsupportedApi { api } => write!(f, "the {:?} API isn't supported", api),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::ShaderCompilation { .. } => "shader compilation failed",
+            Error::ProgramLink { .. } => "program linking failed",
+            Error::UnknownUniform { .. } => "unknown uniform",
+            Error::UniformTypeMismatch { .. } => "uniform type mismatch",
+            Error::IncompleteFramebuffer { .. } => "incomplete framebuffer",
+            Error::ObjectCreation { .. } => "object creation failed",
+            Error::UnsupportedApi { .. } => "unsupported API",
+        }
+    }
+}
+
+/// A programmable stage of the GL pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl Display for ShaderStage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ShaderStage::Vertex => f.write_str("vertex"),
+            ShaderStage::Fragment => f.write_str("fragment"),
+        }
+    }
+}
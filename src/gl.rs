@@ -0,0 +1,19 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The GL bindings that the crate draws with, generated for OpenGL 3.3 core.
+//!
+//! The constants and types here are the same as in any other set of bindings, so they can be used
+//! with the crate's API, such as `RenderTarget::with_format()`. The functions are methods of
+//! `Gl`, which `lord_drawquaad::Gl` dereferences to.
+
+#![allow(clippy::all)]
+
+include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
@@ -0,0 +1,342 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Drawing through `glow`, for applications built on it rather than on raw GL bindings.
+
+use {Api, DrawOptions, Error, PerTarget, QuadUniforms, TextureTarget};
+use {VERTICES, Vertex};
+use capabilities;
+use error::ShaderStage;
+use program::{self, POSITION_ATTRIBUTE, TEX_COORD_ATTRIBUTE};
+use shaders;
+use state::{self, BlendGuard, StateFunctions};
+
+use glow::{self, HasContext};
+use std::mem;
+use std::slice;
+use std::sync::Arc;
+
+/// Draws textured quads through a `glow::Context`, such as the one that `egui_glow` passes to
+/// paint callbacks.
+///
+/// This draws with the same shaders as `Context`, and accepts the same `DrawOptions`, but takes
+/// `glow` textures instead of texture names. Only `Api::Gl` and `Api::Gles` are supported.
+///
+/// Drawing changes the current program, vertex array, active texture unit, and the binding of
+/// `GL_TEXTURE0`. Blending state is restored afterward, as with `Context`.
+pub struct GlowContext {
+    programs: PerTarget<GlowProgram>,
+    vertex_array: glow::VertexArray,
+    vertex_buffer: glow::Buffer,
+    api: Api,
+    gl: Arc<glow::Context>,
+}
+
+impl GlowContext {
+    /// Creates a context that draws through the given `glow` context, which must be current.
+    ///
+    /// Panics if the shaders fail to compile or link, or if the GL context is older than 3.3
+    /// (or ES 3.0). Use `GlowContext::try_new()` if you want to handle those cases yourself.
+    pub fn new(gl: Arc<glow::Context>) -> GlowContext {
+        match GlowContext::try_new(gl) {
+            Ok(context) => context,
+            Err(err) => panic!("failed to create a `lord_drawquaad::GlowContext`: {}", err),
+        }
+    }
+
+    /// Creates a context that draws through the given `glow` context, which must be current,
+    /// returning an error if the shaders could not be compiled or linked, the GL context is too
+    /// old, or the driver fails to create an object.
+    pub fn try_new(gl: Arc<glow::Context>) -> Result<GlowContext, Error> {
+        unsafe {
+            let version_string = gl.get_parameter_string(glow::VERSION);
+            let (es, version) = capabilities::parse_gl_version(&version_string);
+            let glsl_string = gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION);
+            let glsl_version = capabilities::parse_version(&glsl_string);
+            let api = capabilities::choose_api(es, version, glsl_version);
+            if api == Api::LegacyGl {
+                // Legacy shaders need the size of each texture, which `glow` can't query.
+                return Err(Error::UnsupportedApi { api: api })
+            }
+            let rectangle_textures = capabilities::supports_rectangle_textures(es, version, |name| {
+                gl.supported_extensions().contains(name)
+            });
+
+            let programs = PerTarget::new(rectangle_textures, |target| {
+                GlowProgram::new(&gl, api, target)
+            })?;
+
+            let vertex_buffer = gl.create_buffer().map_err(|message| {
+                Error::ObjectCreation { object: "vertex buffer", message: message }
+            })?;
+            let vertex_array = match gl.create_vertex_array() {
+                Ok(vertex_array) => vertex_array,
+                Err(message) => {
+                    gl.delete_buffer(vertex_buffer);
+                    return Err(Error::ObjectCreation { object: "vertex array", message: message })
+                }
+            };
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER,
+                                    slice::from_raw_parts(VERTICES.as_ptr() as *const u8,
+                                                          mem::size_of_val(&VERTICES)),
+                                    glow::STATIC_DRAW);
+            gl.vertex_attrib_pointer_f32(POSITION_ATTRIBUTE,
+                                         2,
+                                         glow::FLOAT,
+                                         false,
+                                         mem::size_of::<Vertex>() as i32,
+                                         0);
+            gl.vertex_attrib_pointer_f32(TEX_COORD_ATTRIBUTE,
+                                         2,
+                                         glow::FLOAT,
+                                         false,
+                                         mem::size_of::<Vertex>() as i32,
+                                         (mem::size_of::<f32>() * 2) as i32);
+            gl.enable_vertex_attrib_array(POSITION_ATTRIBUTE);
+            gl.enable_vertex_attrib_array(TEX_COORD_ATTRIBUTE);
+
+            Ok(GlowContext {
+                programs: programs,
+                vertex_array: vertex_array,
+                vertex_buffer: vertex_buffer,
+                api: api,
+                gl: gl,
+            })
+        }
+    }
+
+    /// Draws the given texture, which must be of the type given by `default_target()`, to the
+    /// full viewport.
+    ///
+    /// This behaves like `Context::draw()`; use `draw_with_target()` for other types.
+    pub fn draw(&self, texture: glow::Texture) {
+        self.draw_with_target(texture, self.default_target())
+    }
+
+    /// Draws the given texture, which must be of the given type, to the full viewport.
+    pub fn draw_with_target(&self, texture: glow::Texture, target: TextureTarget) {
+        self.draw_with_options(texture, &DrawOptions {
+            target: target,
+            ..DrawOptions::default()
+        })
+    }
+
+    /// Draws the given texture with all options specified explicitly.
+    pub fn draw_with_options(&self, texture: glow::Texture, options: &DrawOptions) {
+        let gl = &*self.gl;
+        let program = self.programs.get(options.target);
+        unsafe {
+            let mut viewport = [0, 0, 1, 1];
+            if options.needs_viewport() {
+                gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            }
+            let quad = QuadUniforms::new(options, viewport, false);
+            if let Some(background) = options.background {
+                state::clear_rect(gl, quad.background_rect, background);
+            }
+            let _blend_guard = options.blend.map(|mode| BlendGuard::new(gl, mode));
+
+            gl.use_program(Some(program.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(options.target.gl_target(), Some(texture));
+            gl.uniform_1_i32(program.texture.as_ref(), 0);
+
+            let orientation = quad.orientation;
+            let transform: Vec<f32> = quad.transform.iter()
+                                                    .flat_map(|column| column.iter().cloned())
+                                                    .collect();
+            gl.uniform_4_f32_slice(program.src_rect.as_ref(), &quad.src_rect);
+            gl.uniform_1_i32(program.src_in_texels.as_ref(), quad.src_in_texels);
+            gl.uniform_matrix_2_f32_slice(program.orientation.as_ref(), false, &[
+                orientation[0][0], orientation[0][1],
+                orientation[1][0], orientation[1][1],
+            ]);
+            gl.uniform_1_i32(program.fit_mode.as_ref(), quad.fit_mode);
+            gl.uniform_2_f32_slice(program.dest_size.as_ref(), &quad.dest_size);
+            gl.uniform_1_i32(program.filter.as_ref(), quad.filter);
+            gl.uniform_1_i32(program.alpha_conversion.as_ref(), quad.alpha_conversion);
+            gl.uniform_4_f32_slice(program.dest_transform.as_ref(), &quad.dest_transform);
+            gl.uniform_matrix_4_f32_slice(program.transform.as_ref(), false, &transform);
+
+            gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+
+    /// Returns the API that this context draws with.
+    #[inline]
+    pub fn api(&self) -> Api {
+        self.api
+    }
+
+    /// Returns the type of texture that `draw()` expects: `TextureTarget::Rectangle` if the GL
+    /// context has rectangle textures, and `TextureTarget::Texture2D` otherwise, as in GL ES and
+    /// WebGL.
+    #[inline]
+    pub fn default_target(&self) -> TextureTarget {
+        self.programs.default_target()
+    }
+
+    /// Returns the `glow` context that this context draws through.
+    #[inline]
+    pub fn gl(&self) -> &Arc<glow::Context> {
+        &self.gl
+    }
+}
+
+impl Drop for GlowContext {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_vertex_array(self.vertex_array);
+            self.gl.delete_buffer(self.vertex_buffer);
+        }
+    }
+}
+
+/// A linked quad program and the locations of its built-in uniforms.
+struct GlowProgram {
+    program: glow::Program,
+    texture: Option<glow::UniformLocation>,
+    src_rect: Option<glow::UniformLocation>,
+    src_in_texels: Option<glow::UniformLocation>,
+    orientation: Option<glow::UniformLocation>,
+    fit_mode: Option<glow::UniformLocation>,
+    dest_size: Option<glow::UniformLocation>,
+    filter: Option<glow::UniformLocation>,
+    alpha_conversion: Option<glow::UniformLocation>,
+    dest_transform: Option<glow::UniformLocation>,
+    transform: Option<glow::UniformLocation>,
+    gl: Arc<glow::Context>,
+}
+
+impl GlowProgram {
+    /// Builds the program for drawing single quads of textures of the given type with the default
+    /// fragment shader, from the same sources as `Context` uses.
+    unsafe fn new(gl: &Arc<glow::Context>, api: Api, target: TextureTarget)
+                  -> Result<GlowProgram, Error> {
+        let vertex_source = shaders::vertex_source(shaders::VERTEX_SHADER, target, api);
+        let fragment_source = shaders::fragment_source(shaders::FRAGMENT_SHADER, target, api);
+        let vertex_shader = compile_shader(gl, ShaderStage::Vertex, &vertex_source)?;
+        let fragment_shader = match compile_shader(gl, ShaderStage::Fragment, &fragment_source) {
+            Ok(fragment_shader) => fragment_shader,
+            Err(err) => {
+                gl.delete_shader(vertex_shader);
+                return Err(err)
+            }
+        };
+
+        let program = match gl.create_program() {
+            Ok(program) => program,
+            Err(message) => {
+                gl.delete_shader(fragment_shader);
+                gl.delete_shader(vertex_shader);
+                return Err(Error::ObjectCreation { object: "program", message: message })
+            }
+        };
+        gl.attach_shader(program, vertex_shader);
+        gl.attach_shader(program, fragment_shader);
+        gl.bind_attrib_location(program, POSITION_ATTRIBUTE, "aPosition");
+        gl.bind_attrib_location(program, TEX_COORD_ATTRIBUTE, "aTexCoord");
+        gl.link_program(program);
+
+        // The shaders aren't needed once the program is linked.
+        gl.detach_shader(program, fragment_shader);
+        gl.detach_shader(program, vertex_shader);
+        gl.delete_shader(fragment_shader);
+        gl.delete_shader(vertex_shader);
+
+        if !gl.get_program_link_status(program) {
+            let log = gl.get_program_info_log(program);
+            gl.delete_program(program);
+            return Err(Error::ProgramLink { log: log })
+        }
+
+        let location = |name| gl.get_uniform_location(program, name);
+        Ok(GlowProgram {
+            program: program,
+            texture: location("uTexture"),
+            src_rect: location("uSrcRect"),
+            src_in_texels: location("uSrcInTexels"),
+            orientation: location("uOrientation"),
+            fit_mode: location("uFitMode"),
+            dest_size: location("uDestSize"),
+            filter: location("uFilter"),
+            alpha_conversion: location("uAlphaConversion"),
+            dest_transform: location("uDestTransform"),
+            transform: location("uTransform"),
+            gl: gl.clone(),
+        })
+    }
+}
+
+impl Drop for GlowProgram {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_program(self.program);
+        }
+    }
+}
+
+unsafe fn compile_shader(gl: &glow::Context, stage: ShaderStage, source: &str)
+                         -> Result<glow::Shader, Error> {
+    let shader = gl.create_shader(program::shader_type(stage)).map_err(|message| {
+        Error::ObjectCreation { object: "shader", message: message }
+    })?;
+    gl.shader_source(shader, source);
+    gl.compile_shader(shader);
+    if gl.get_shader_compile_status(shader) {
+        return Ok(shader)
+    }
+
+    let log = gl.get_shader_info_log(shader);
+    gl.delete_shader(shader);
+    Err(program::compilation_error(stage, log, source))
+}
+
+impl StateFunctions for glow::Context {
+    unsafe fn is_enabled(&self, capability: u32) -> bool {
+        HasContext::is_enabled(self, capability)
+    }
+
+    unsafe fn set_enabled(&self, capability: u32, enabled: bool) {
+        if enabled {
+            self.enable(capability)
+        } else {
+            self.disable(capability)
+        }
+    }
+
+    unsafe fn get_integers(&self, name: u32, values: &mut [i32]) {
+        self.get_parameter_i32_slice(name, values)
+    }
+
+    unsafe fn get_floats(&self, name: u32, values: &mut [f32]) {
+        self.get_parameter_f32_slice(name, values)
+    }
+
+    unsafe fn set_blend(&self, equations: [u32; 2], factors: [u32; 4]) {
+        self.blend_equation_separate(equations[0], equations[1]);
+        self.blend_func_separate(factors[0], factors[1], factors[2], factors[3]);
+    }
+
+    unsafe fn set_scissor(&self, rect: [i32; 4]) {
+        self.scissor(rect[0], rect[1], rect[2], rect[3])
+    }
+
+    unsafe fn set_clear_color(&self, color: [f32; 4]) {
+        self.clear_color(color[0], color[1], color[2], color[3])
+    }
+
+    unsafe fn clear_color_buffer(&self) {
+        self.clear(glow::COLOR_BUFFER_BIT)
+    }
+}
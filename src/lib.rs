@@ -8,141 +8,1152 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! A minimalist, dead-simple library to draw full-screen textured quads in OpenGL 3.3+.
+//! A minimalist, dead-simple library to draw full-screen textured quads in OpenGL 3.3+ and
+//! OpenGL ES 3.0+, with a fallback for OpenGL 2.1.
 //!
 //! The goal is to factor out the annoying shader boilerplate.
 //!
-//! You should not use this library if you're particularly concerned about performance. It doesn't
-//! do any batching.
+//! You should not use this library if you're particularly concerned about performance. Each call
+//! to `Context::draw()` issues its own draw call; if you have many quads to draw, collect them in
+//! a `Batch` and draw them with `Context::draw_batch()` instead.
+//!
+//! The crate calls GL through its own table of function pointers, a `Gl`, rather than through
+//! global bindings. Load one from your windowing library with `Gl::load_with()` and pass it to
+//! `Context::new()`; the rest of your application can use whatever bindings it likes.
+//! Applications built on `glow` can enable the `glow` feature and draw with a `GlowContext`
+//! instead.
+//!
+//! With the `image` feature enabled, `Texture`s can be created from, and `RenderTarget`s read back
+//! into, buffers from the `image` crate.
 
-extern crate gl;
+#[cfg(feature = "glow")]
+extern crate glow;
+#[cfg(feature = "image")]
+extern crate image;
 
-use gl::types::{GLchar, GLint, GLsizei, GLsizeiptr, GLuint, GLvoid};
+use gl::types::{GLenum, GLint, GLsizei, GLsizeiptr, GLuint, GLvoid};
+use batch::BatchVertex;
+use program::{POSITION_ATTRIBUTE, Program, TEX_COORD_ATTRIBUTE};
+use state::{BlendGuard, StateGuard};
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::mem;
 use std::os::raw::c_void;
+use std::ptr;
+
+pub use batch::Batch;
+pub use capabilities::{Api, Capabilities, Profile};
+pub use error::{Error, ShaderStage};
+#[cfg(feature = "glow")]
+pub use glow_context::GlowContext;
+pub use loader::Gl;
+pub use target::RenderTarget;
+pub use texture::{Texture, TextureFilter, TextureFormat, TextureOptions, TextureWrap};
+pub use uniform::UniformValue;
+pub use yuv::{YuvColorSpace, YuvOptions, YuvPlanes, YuvRange};
+
+pub mod gl;
+
+mod batch;
+mod capabilities;
+mod error;
+#[cfg(feature = "glow")]
+mod glow_context;
+mod loader;
+mod program;
+mod shaders;
+mod state;
+mod target;
+mod texture;
+mod uniform;
+mod yuv;
 
 pub struct Context {
-    vertex_shader: GLuint,
-    fragment_shader: GLuint,
-    program: GLuint,
-    texture_uniform: GLint,
+    programs: PerTarget<Program>,
     vertex_array: GLuint,
     vertex_buffer: GLuint,
+    batch_programs: PerTarget<Program>,
+    batch_vertex_array: GLuint,
+    batch_vertex_buffer: GLuint,
+    yuv_programs: PerTarget<Program>,
+    capabilities: Capabilities,
+    preserve_state: bool,
+    uniforms: HashMap<String, UniformValue>,
+    /// Whether we're rendering into a `RenderTarget`, in which case clip space is flipped
+    /// vertically so that its texture ends up top-down.
+    flip_y: Cell<bool>,
+    gl: Gl,
 }
 
 impl Context {
     /// Creates a context, encapsulating the state necessary to draw textured quads.
     ///
-    /// You must have a current valid GL context before calling this.
-    pub fn new() -> Context {
+    /// You must have a current valid GL context, which `gl` was loaded for, before calling this.
+    ///
+    /// Panics if the shaders fail to compile or link. Use `Context::try_new()` if you want to
+    /// handle that case yourself.
+    pub fn new(gl: &Gl) -> Context {
+        match Context::try_new(gl) {
+            Ok(context) => context,
+            Err(err) => panic!("failed to create a `lord_drawquaad::Context`: {}", err),
+        }
+    }
+
+    /// Creates a context, encapsulating the state necessary to draw textured quads, returning an
+    /// error if the shaders could not be compiled or linked.
+    ///
+    /// You must have a current valid GL context, which `gl` was loaded for, before calling this.
+    pub fn try_new(gl: &Gl) -> Result<Context, Error> {
+        Context::with_options(gl, &ContextOptions::default())
+    }
+
+    /// Creates a context with the given options, returning an error if the shaders could not be
+    /// compiled or linked, or if the API can't be drawn with in this context.
+    ///
+    /// You must have a current valid GL context, which `gl` was loaded for, before calling this.
+    pub fn with_options(gl: &Gl, options: &ContextOptions) -> Result<Context, Error> {
+        let mut capabilities = Capabilities::current(gl);
+        if let Some(api) = options.api {
+            capabilities.api = api
+        }
+        let api = capabilities.api;
+        // Core profiles needn't accept GLSL 1.20, and can't draw without a vertex array object.
+        if api == Api::LegacyGl && capabilities.profile == Profile::Core {
+            return Err(Error::UnsupportedApi { api: api })
+        }
+        let rectangle_textures = capabilities.rectangle_textures && api != Api::Gles;
+        let _guard = StateGuard::new(gl, options.preserve_state, &capabilities, 1);
+        let fragment_body = match options.fragment_shader {
+            Some(ref fragment_shader) => &fragment_shader[..],
+            None => shaders::FRAGMENT_SHADER,
+        };
         unsafe {
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(vertex_shader,
-                             1,
-                             &(VERTEX_SHADER.as_ptr() as *const GLchar),
-                             &(VERTEX_SHADER.len() as GLint));
-            gl::ShaderSource(fragment_shader,
-                             1,
-                             &(FRAGMENT_SHADER.as_ptr() as *const GLchar),
-                             &(FRAGMENT_SHADER.len() as GLint));
-            gl::CompileShader(vertex_shader);
-            gl::CompileShader(fragment_shader);
-
-            let program = gl::CreateProgram();
-            gl::AttachShader(program, vertex_shader);
-            gl::AttachShader(program, fragment_shader);
-            gl::LinkProgram(program);
-            gl::UseProgram(program);
-
-            let position_attribute =
-                gl::GetAttribLocation(program, "aPosition\0".as_ptr() as *const GLchar);
-            let tex_coord_attribute =
-                gl::GetAttribLocation(program, "aTexCoord\0".as_ptr() as *const GLchar);
-            let texture_uniform =
-                gl::GetUniformLocation(program, "uTexture\0".as_ptr() as *const GLchar);
-
-            let mut vertex_array = 0;
-            gl::GenVertexArrays(1, &mut vertex_array);
-            gl::BindVertexArray(vertex_array);
-
-            let mut vertex_buffer = 0;
-            gl::GenBuffers(1, &mut vertex_buffer);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           mem::size_of::<Vertex>() as GLsizeiptr * 4,
-                           VERTICES.as_ptr() as *const c_void,
-                           gl::STATIC_DRAW);
-
-            gl::VertexAttribPointer(position_attribute as GLuint,
-                                    2,
-                                    gl::FLOAT,
-                                    gl::FALSE,
-                                    mem::size_of::<Vertex>() as GLsizei,
-                                    (mem::size_of::<f32>() * 0) as *const GLvoid);
-            gl::VertexAttribPointer(tex_coord_attribute as GLuint,
-                                    2,
-                                    gl::FLOAT,
-                                    gl::FALSE,
-                                    mem::size_of::<Vertex>() as GLsizei,
-                                    (mem::size_of::<f32>() * 2) as *const GLvoid);
-            gl::EnableVertexAttribArray(position_attribute as GLuint);
-            gl::EnableVertexAttribArray(tex_coord_attribute as GLuint);
-
-            Context {
-                vertex_shader: vertex_shader,
-                fragment_shader: fragment_shader,
-                program: program,
-                texture_uniform: texture_uniform,
-                vertex_array: vertex_array,
-                vertex_buffer: vertex_buffer,
+            let programs = PerTarget::new(rectangle_textures, |target| {
+                Program::for_target(gl, api, target, fragment_body)
+            })?;
+            let batch_programs = PerTarget::new(rectangle_textures, |target| {
+                Program::batch_for_target(gl, api, target, fragment_body)
+            })?;
+            let yuv_programs = PerTarget::new(rectangle_textures, |target| {
+                Program::for_target(gl, api, target, shaders::YUV_FRAGMENT_SHADER)
+            })?;
+            gl.UseProgram(programs.texture_2d.program);
+
+            let mut vertex_buffers = [0; 2];
+            gl.GenBuffers(2, vertex_buffers.as_mut_ptr());
+            gl.BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[0]);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          mem::size_of::<Vertex>() as GLsizeiptr * 4,
+                          VERTICES.as_ptr() as *const c_void,
+                          gl::STATIC_DRAW);
+
+            // Legacy GL may not have vertex array objects, so the attributes are set up on every
+            // draw there instead.
+            let mut vertex_arrays = [0; 2];
+            if api != Api::LegacyGl {
+                gl.GenVertexArrays(2, vertex_arrays.as_mut_ptr());
+
+                gl.BindVertexArray(vertex_arrays[1]);
+                gl.BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[1]);
+                set_batch_attributes(gl);
+
+                gl.BindVertexArray(vertex_arrays[0]);
+                gl.BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[0]);
+                set_quad_attributes(gl);
             }
+
+            Ok(Context {
+                programs: programs,
+                vertex_array: vertex_arrays[0],
+                vertex_buffer: vertex_buffers[0],
+                batch_programs: batch_programs,
+                batch_vertex_array: vertex_arrays[1],
+                batch_vertex_buffer: vertex_buffers[1],
+                yuv_programs: yuv_programs,
+                capabilities: capabilities,
+                preserve_state: options.preserve_state,
+                uniforms: HashMap::new(),
+                flip_y: Cell::new(false),
+                gl: gl.clone(),
+            })
         }
     }
 
+    /// Creates a context that draws with the given fragment shader instead of the default one,
+    /// returning an error if it fails to compile or link.
+    ///
+    /// This is shorthand for `Context::with_options()` with `ContextOptions::fragment_shader`
+    /// set; see the documentation there for what the shader can use.
+    pub fn with_fragment_shader(gl: &Gl, source: &str) -> Result<Context, Error> {
+        Context::with_options(gl, &ContextOptions {
+            fragment_shader: Some(source.to_owned()),
+            ..ContextOptions::default()
+        })
+    }
+
     /// Draws the given texture to the full viewport.
     ///
     /// *The texture must be of `GL_TEXTURE_RECTANGLE` type, not `GL_TEXTURE_2D`.* (This is for
-    /// compatibility with macOS, which can only bind `IOSurface`s to texture rectangles.)
+    /// compatibility with macOS, which can only bind `IOSurface`s to texture rectangles.) Use
+    /// `draw_with_target()` to draw a `GL_TEXTURE_2D` texture. In GL contexts without rectangle
+    /// textures, such as GL ES ones, the texture must be `GL_TEXTURE_2D` instead; the type
+    /// expected is given by `default_target()`.
     ///
-    /// If you want to draw to a subrect, simply call `gl::Viewport()` before calling this. If you
-    /// want to draw only a portion of the texture, set the scissor box with `gl::Scissor()` and
-    /// enable it with `gl::Enable(gl::SCISSOR_TEST)` before calling this. You can also use the
-    /// stencil buffer for more advanced effects.
+    /// If you want to draw to a subrect, use `draw_rect()`. If you want to draw only a portion of
+    /// the texture, use `draw_region()`. If you want to clip the output, set the scissor box with
+    /// `gl.Scissor()` and enable it with `gl.Enable(gl::SCISSOR_TEST)` before calling this. You
+    /// can also use the stencil buffer for more advanced effects.
     ///
     /// Remember to set magnification and minification filters on the texture first
     /// (`GL_TEXTURE_MIN_FILTER` and `GL_TEXTURE_MAG_FILTER`).
     ///
     /// The same context that was current at the time `Context::new()` was called must be current
     /// at the time this is called.
+    ///
+    /// This changes the current program, vertex array (in legacy GL, vertex attributes 0 and 1),
+    /// `GL_ARRAY_BUFFER` binding, active texture unit, and texture bindings, unless the context
+    /// was created with `ContextOptions::preserve_state` set.
     pub fn draw(&self, texture: GLuint) {
+        self.draw_with_options(texture, &self.default_options())
+    }
+
+    /// Draws the given texture, which must be of the given type, to the full viewport.
+    ///
+    /// Aside from the texture target, this behaves exactly like `draw()`.
+    pub fn draw_with_target(&self, texture: GLuint, target: TextureTarget) {
+        self.draw_with_options(texture, &DrawOptions {
+            target: target,
+            ..DrawOptions::default()
+        })
+    }
+
+    /// Draws the given texture rectangle into the given rectangle of the viewport.
+    ///
+    /// The viewport itself is left untouched, so you can compose several quads into one
+    /// framebuffer without saving and restoring it.
+    pub fn draw_rect(&self, texture: GLuint, dest: Rect) {
+        self.draw_with_options(texture, &DrawOptions {
+            dest: dest,
+            ..self.default_options()
+        })
+    }
+
+    /// Draws the `src` rectangle of the given texture rectangle, stretched to fill the `dest`
+    /// rectangle of the viewport.
+    ///
+    /// A `src` rectangle measured in `Units::Pixels` is in texels. This is useful for sprite
+    /// atlases and tiled video frames.
+    pub fn draw_region(&self, texture: GLuint, src: Rect, dest: Rect) {
+        self.draw_with_options(texture, &DrawOptions {
+            src: src,
+            dest: dest,
+            ..self.default_options()
+        })
+    }
+
+    /// Draws the given texture rectangle to the full viewport, then applies `transform` to the
+    /// quad in clip space.
+    ///
+    /// This can rotate, scale, skew, and apply perspective to the quad. Note that clip space
+    /// spans from -1 to 1 in both directions regardless of the aspect ratio of the viewport, so
+    /// rotations should be surrounded by scales that correct for it.
+    pub fn draw_transformed(&self, texture: GLuint, transform: Transform) {
+        self.draw_with_options(texture, &DrawOptions {
+            transform: transform,
+            ..self.default_options()
+        })
+    }
+
+    /// Draws the given texture with all options specified explicitly.
+    pub fn draw_with_options(&self, texture: GLuint, options: &DrawOptions) {
+        self.draw_multi_with_options(&[("uTexture", texture, options.target)], options)
+    }
+
+    /// Draws a texture owned by the crate to fill the viewport.
+    pub fn draw_texture(&self, texture: &Texture) {
+        self.draw_texture_with_options(texture, &DrawOptions::default())
+    }
+
+    /// Draws a texture owned by the crate with all options specified explicitly, except that
+    /// `DrawOptions::target` is taken from the texture.
+    pub fn draw_texture_with_options(&self, texture: &Texture, options: &DrawOptions) {
+        self.draw_with_options(texture.id(), &DrawOptions {
+            target: texture.target(),
+            ..*options
+        })
+    }
+
+    /// Draws with several input textures at once, for custom fragment shaders that combine them
+    /// (YUV planes, masks, blending two frames, and so on).
+    ///
+    /// Each entry names a sampler uniform in the shader, the texture to bind to it, and the
+    /// texture's type. The textures are bound to consecutive texture units starting from
+    /// `GL_TEXTURE0`, so at most `Capabilities::max_texture_units` may be passed; this panics if
+    /// there are more. Entries whose sampler isn't active in the shader are bound but otherwise
+    /// ignored.
+    ///
+    /// The crate's own `uTexture` sampler can be bound like any other. Its type must match
+    /// `DrawOptions::target` (for `draw_multi()`, `default_target()`), which selects the variant
+    /// of the shader to draw with.
+    pub fn draw_multi(&self, textures: &[(&str, GLuint, TextureTarget)]) {
+        self.draw_multi_with_options(textures, &self.default_options())
+    }
+
+    /// Draws with several input textures at once, as in `draw_multi()`, with all options
+    /// specified explicitly.
+    pub fn draw_multi_with_options(&self,
+                                   textures: &[(&str, GLuint, TextureTarget)],
+                                   options: &DrawOptions) {
+        let max_textures = self.capabilities.max_texture_units as usize;
+        assert!(textures.len() <= max_textures,
+                "at most {} textures can be drawn at once",
+                max_textures);
+        self.draw_quad(self.programs.get(options.target), textures, &[], options)
+    }
+
+    /// Draws a planar YUV video frame to the full viewport, converting it to RGB.
+    ///
+    /// The planes must be of the type given by `default_target()`. Use `draw_yuv_with_options()`
+    /// for other types and the other drawing options.
+    pub fn draw_yuv(&self, planes: YuvPlanes, yuv: &YuvOptions) {
+        self.draw_yuv_with_options(planes, yuv, &self.default_options())
+    }
+
+    /// Draws a planar YUV video frame, converting it to RGB, with all options specified
+    /// explicitly.
+    pub fn draw_yuv_with_options(&self,
+                                 planes: YuvPlanes,
+                                 yuv: &YuvOptions,
+                                 options: &DrawOptions) {
+        let target = options.target;
+        let (matrix, offset) = yuv.conversion();
+        let uniforms = [
+            ("uYuvMatrix", UniformValue::Mat3(matrix)),
+            ("uYuvOffset", UniformValue::Vec3(offset)),
+            ("uInterleavedChroma", UniformValue::Int(match planes {
+                YuvPlanes::Nv12 { .. } => 1,
+                YuvPlanes::I420 { .. } => 0,
+            })),
+        ];
+        let program = self.yuv_programs.get(target);
+        match planes {
+            YuvPlanes::Nv12 { y, uv } => {
+                self.draw_quad(program,
+                               &[("uTexture", y, target), ("uTextureU", uv, target)],
+                               &uniforms,
+                               options)
+            }
+            YuvPlanes::I420 { y, u, v } => {
+                self.draw_quad(program,
+                               &[
+                                   ("uTexture", y, target),
+                                   ("uTextureU", u, target),
+                                   ("uTextureV", v, target),
+                               ],
+                               &uniforms,
+                               options)
+            }
+        }
+    }
+
+    /// Draws a single quad with the given program, binding the given textures to consecutive
+    /// texture units and setting the given uniforms in addition to those set with
+    /// `set_uniform()`.
+    fn draw_quad(&self,
+                 program: &Program,
+                 textures: &[(&str, GLuint, TextureTarget)],
+                 uniforms: &[(&str, UniformValue)],
+                 options: &DrawOptions) {
+        let gl = &self.gl;
+        let _guard = StateGuard::new(gl, self.preserve_state, &self.capabilities, textures.len());
+        let viewport = if options.needs_viewport() { viewport(gl) } else { [0, 0, 1, 1] };
+        let quad = QuadUniforms::new(options, viewport, self.flip_y.get());
+        if let Some(background) = options.background {
+            unsafe {
+                state::clear_rect(gl, quad.background_rect, background);
+            }
+        }
+
+        let _blend_guard = options.blend.map(|mode| BlendGuard::new(gl, mode));
+        unsafe {
+            gl.UseProgram(program.program);
+            self.upload_uniforms(program);
+            for &(name, ref value) in uniforms {
+                if let Some(uniform) = program.uniforms.get(name) {
+                    value.upload(gl, uniform.location)
+                }
+            }
+            self.bind_vertices(self.vertex_array, self.vertex_buffer, set_quad_attributes);
+
+            for (unit, &(name, texture, target)) in textures.iter().enumerate() {
+                gl.ActiveTexture(gl::TEXTURE0 + unit as GLenum);
+                gl.BindTexture(target.gl_target(), texture);
+                if let Some(uniform) = program.uniforms.get(name) {
+                    gl.Uniform1i(uniform.location, unit as GLint);
+                }
+                self.upload_texture_size(program, name, target);
+            }
+
+            gl.Uniform4fv(program.src_rect_uniform, 1, quad.src_rect.as_ptr());
+            gl.Uniform1i(program.src_in_texels_uniform, quad.src_in_texels);
+            gl.UniformMatrix2fv(program.orientation_uniform,
+                                1,
+                                gl::FALSE,
+                                quad.orientation[0].as_ptr());
+            gl.Uniform1i(program.fit_mode_uniform, quad.fit_mode);
+            gl.Uniform2fv(program.dest_size_uniform, 1, quad.dest_size.as_ptr());
+            gl.Uniform1i(program.filter_uniform, quad.filter);
+            gl.Uniform1i(program.alpha_conversion_uniform, quad.alpha_conversion);
+            gl.Uniform4fv(program.dest_transform_uniform, 1, quad.dest_transform.as_ptr());
+            gl.UniformMatrix4fv(program.transform_uniform,
+                                1,
+                                gl::FALSE,
+                                quad.transform[0].as_ptr());
+
+            gl.DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            self.unbind_vertices();
+        }
+    }
+
+    /// Draws all the quads in the given batch, using as few draw calls as possible.
+    ///
+    /// The texture parameters and GL context requirements are the same as for `draw()`.
+    pub fn draw_batch(&self, batch: &Batch) {
+        if batch.is_empty() {
+            return
+        }
+
+        let gl = &self.gl;
+        let viewport_size = if batch.needs_viewport_size() {
+            viewport_size(gl)
+        } else {
+            (1.0, 1.0)
+        };
+        let mut vertices = Vec::with_capacity(batch.len() * 6);
+        let groups = batch.build(&mut vertices, viewport_size);
+        if self.flip_y.get() {
+            for vertex in &mut vertices {
+                vertex.position[1] = -vertex.position[1]
+            }
+        }
+
+        let _guard = StateGuard::new(gl, self.preserve_state, &self.capabilities, 1);
+        let target = batch.target().unwrap_or(self.default_target());
+        let program = self.batch_programs.get(target);
         unsafe {
-            gl::UseProgram(self.program);
-            gl::BindVertexArray(self.vertex_array);
+            gl.UseProgram(program.program);
+            self.upload_uniforms(program);
+            self.bind_vertices(self.batch_vertex_array,
+                               self.batch_vertex_buffer,
+                               set_batch_attributes);
+            gl.BufferData(gl::ARRAY_BUFFER,
+                          (mem::size_of::<BatchVertex>() * vertices.len()) as GLsizeiptr,
+                          ptr::null(),
+                          gl::STREAM_DRAW);
+            gl.BufferSubData(gl::ARRAY_BUFFER,
+                             0,
+                             (mem::size_of::<BatchVertex>() * vertices.len()) as GLsizeiptr,
+                             vertices.as_ptr() as *const c_void);
 
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.Uniform1i(program.texture_uniform, 0);
 
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_RECTANGLE, texture);
-            gl::Uniform1i(self.texture_uniform, 0);
+            let mut first = 0;
+            for (texture, count) in groups {
+                gl.BindTexture(target.gl_target(), texture);
+                self.upload_texture_size(program, "uTexture", target);
+                gl.DrawArrays(gl::TRIANGLES, first as GLint, count as GLsizei);
+                first += count;
+            }
+            self.unbind_vertices();
+        }
+    }
 
-            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+    /// Draws into the given render target for the duration of the closure `f`.
+    ///
+    /// This binds the target's framebuffer and sets the viewport to cover it, then restores the
+    /// previous framebuffer binding and viewport afterward. While `f` runs, everything this
+    /// context draws is flipped vertically, so that the target's texture is top-down like every
+    /// other texture. (Drawing done with raw GL calls isn't affected.)
+    pub fn render_to<F, R>(&self, target: &RenderTarget, f: F) -> R where F: FnOnce() -> R {
+        let gl = &self.gl;
+        let (width, height) = target.size();
+        let _guard = RenderToGuard::new(self);
+        unsafe {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer());
+            gl.Viewport(0, 0, width as GLsizei, height as GLsizei);
+        }
+        self.flip_y.set(true);
+        f()
+    }
+
+    /// Draws the given texture into the given render target, with all options specified
+    /// explicitly.
+    ///
+    /// This is shorthand for a `render_to()` call that draws a single texture.
+    pub fn draw_to(&self, target: &RenderTarget, texture: GLuint, options: &DrawOptions) {
+        self.render_to(target, || self.draw_with_options(texture, options))
+    }
+
+    /// Sets a uniform in the context's fragment shader, for use with custom shaders supplied via
+    /// `ContextOptions::fragment_shader`.
+    ///
+    /// The value can be anything convertible to a `UniformValue`, for example
+    /// `context.set_uniform("uExposure", 1.5f32)`. It stays in effect for all subsequent draws
+    /// until it's set again. An error is returned if the shader has no active uniform with the
+    /// given name, or if its type doesn't match the value.
+    pub fn set_uniform<V>(&mut self, name: &str, value: V) -> Result<(), Error>
+                          where V: Into<UniformValue> {
+        let value = value.into();
+        let mut found = false;
+        for program in self.programs.all().into_iter().chain(self.batch_programs.all()) {
+            if let Some(uniform) = program.uniforms.get(name) {
+                if !value.matches(uniform.gl_type) {
+                    return Err(Error::UniformTypeMismatch { name: name.to_owned() })
+                }
+                found = true
+            }
+        }
+        if !found {
+            return Err(Error::UnknownUniform { name: name.to_owned() })
+        }
+
+        self.uniforms.insert(name.to_owned(), value);
+        Ok(())
+    }
+
+    /// Returns the API that this context draws with.
+    #[inline]
+    pub fn api(&self) -> Api {
+        self.capabilities.api
+    }
+
+    /// Returns the capabilities of the GL context that were detected when this context was
+    /// created.
+    #[inline]
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Returns the type of texture that `draw()` and the other methods that don't take a
+    /// `TextureTarget` expect: `TextureTarget::Rectangle` if the GL context has rectangle
+    /// textures, and `TextureTarget::Texture2D` otherwise, as in GL ES.
+    #[inline]
+    pub fn default_target(&self) -> TextureTarget {
+        self.programs.default_target()
+    }
+
+    /// Returns the GL functions that this context calls through.
+    #[inline]
+    pub fn gl(&self) -> &Gl {
+        &self.gl
+    }
+
+    /// Returns the default drawing options, with the target set to `default_target()`.
+    fn default_options(&self) -> DrawOptions {
+        DrawOptions {
+            target: self.default_target(),
+            ..DrawOptions::default()
+        }
+    }
+
+    /// Binds the given vertex array and buffer, or, in legacy GL, binds the buffer and points the
+    /// vertex attributes at it with `set_attributes`.
+    unsafe fn bind_vertices(&self,
+                            vertex_array: GLuint,
+                            vertex_buffer: GLuint,
+                            set_attributes: unsafe fn(&Gl)) {
+        let gl = &self.gl;
+        if self.capabilities.api == Api::LegacyGl {
+            gl.BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+            set_attributes(gl);
+        } else {
+            gl.BindVertexArray(vertex_array);
+            gl.BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+        }
+    }
+
+    /// In legacy GL, disables the vertex attributes enabled by `bind_vertices()`, since there's no
+    /// vertex array to contain them.
+    unsafe fn unbind_vertices(&self) {
+        if self.capabilities.api == Api::LegacyGl {
+            let gl = &self.gl;
+            gl.DisableVertexAttribArray(POSITION_ATTRIBUTE);
+            gl.DisableVertexAttribArray(TEX_COORD_ATTRIBUTE);
+        }
+    }
+
+    /// In legacy GL, where shaders can't query texture sizes, sets the size uniform for the named
+    /// sampler to the size of the texture bound to it, which must be bound to the active texture
+    /// unit.
+    unsafe fn upload_texture_size(&self, program: &Program, name: &str, target: TextureTarget) {
+        if self.capabilities.api != Api::LegacyGl {
+            return
+        }
+        if let Some(uniform) = program.uniforms.get(&shaders::size_uniform_name(name)) {
+            let gl = &self.gl;
+            let (mut width, mut height) = (0, 0);
+            gl.GetTexLevelParameteriv(target.gl_target(), 0, gl::TEXTURE_WIDTH, &mut width);
+            gl.GetTexLevelParameteriv(target.gl_target(), 0, gl::TEXTURE_HEIGHT, &mut height);
+            gl.Uniform2f(uniform.location, width as f32, height as f32);
+        }
+    }
+
+    /// Uploads the values set with `set_uniform()` to the given program, which must be current.
+    unsafe fn upload_uniforms(&self, program: &Program) {
+        for (name, value) in &self.uniforms {
+            if let Some(uniform) = program.uniforms.get(name) {
+                value.upload(&self.gl, uniform.location)
+            }
+        }
+    }
+}
+
+/// Restores the framebuffer binding, viewport, and vertical flip that `render_to()` replaces when
+/// dropped, even if drawing panics.
+struct RenderToGuard<'a> {
+    context: &'a Context,
+    old_framebuffer: GLuint,
+    old_viewport: [GLint; 4],
+    old_flip_y: bool,
+}
+
+impl<'a> RenderToGuard<'a> {
+    fn new(context: &'a Context) -> RenderToGuard<'a> {
+        let mut old_framebuffer = 0;
+        unsafe {
+            context.gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut old_framebuffer);
+        }
+        RenderToGuard {
+            context: context,
+            old_framebuffer: old_framebuffer as GLuint,
+            old_viewport: viewport(&context.gl),
+            old_flip_y: context.flip_y.get(),
+        }
+    }
+}
+
+impl<'a> Drop for RenderToGuard<'a> {
+    fn drop(&mut self) {
+        let gl = &self.context.gl;
+        let viewport = self.old_viewport;
+        self.context.flip_y.set(self.old_flip_y);
+        unsafe {
+            gl.Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, self.old_framebuffer);
         }
     }
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
+        let gl = &self.gl;
         unsafe {
-            gl::DeleteBuffers(1, &mut self.vertex_buffer);
-            gl::DeleteVertexArrays(1, &mut self.vertex_array);
-            gl::DeleteProgram(self.program);
-            gl::DeleteShader(self.fragment_shader);
-            gl::DeleteShader(self.vertex_shader);
+            gl.DeleteBuffers(1, &self.batch_vertex_buffer);
+            gl.DeleteBuffers(1, &self.vertex_buffer);
+            if self.capabilities.api != Api::LegacyGl {
+                gl.DeleteVertexArrays(1, &self.batch_vertex_array);
+                gl.DeleteVertexArrays(1, &self.vertex_array);
+            }
+        }
+    }
+}
+
+/// One instance of something for each texture target that the context supports.
+struct PerTarget<T> {
+    /// `None` if the context doesn't have rectangle textures.
+    rectangle: Option<T>,
+    texture_2d: T,
+}
+
+impl<T> PerTarget<T> {
+    fn new<F>(rectangle_textures: bool, mut f: F) -> Result<PerTarget<T>, Error>
+              where F: FnMut(TextureTarget) -> Result<T, Error> {
+        let rectangle = if rectangle_textures {
+            Some(f(TextureTarget::Rectangle)?)
+        } else {
+            None
+        };
+        Ok(PerTarget {
+            rectangle: rectangle,
+            texture_2d: f(TextureTarget::Texture2D)?,
+        })
+    }
+
+    fn get(&self, target: TextureTarget) -> &T {
+        match target {
+            TextureTarget::Rectangle => {
+                self.rectangle.as_ref().expect("this GL context doesn't support rectangle \
+                                                textures; draw with `TextureTarget::Texture2D` \
+                                                instead")
+            }
+            TextureTarget::Texture2D => &self.texture_2d,
+        }
+    }
+
+    /// Returns `TextureTarget::Rectangle` if there's an instance for it, and otherwise
+    /// `TextureTarget::Texture2D`.
+    fn default_target(&self) -> TextureTarget {
+        if self.rectangle.is_some() {
+            TextureTarget::Rectangle
+        } else {
+            TextureTarget::Texture2D
+        }
+    }
+
+    fn all(&self) -> Vec<&T> {
+        self.rectangle.iter().chain(Some(&self.texture_2d)).collect()
+    }
+}
+
+/// The type of a texture to be drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureTarget {
+    /// A `GL_TEXTURE_RECTANGLE` texture, sampled with unnormalized texel coordinates. This is
+    /// the only kind of texture that macOS `IOSurface`s can be bound to.
+    Rectangle,
+    /// An ordinary `GL_TEXTURE_2D` texture.
+    Texture2D,
+}
+
+impl TextureTarget {
+    /// Returns the GL enum value corresponding to this target, suitable for `gl.BindTexture()`.
+    pub fn gl_target(self) -> GLenum {
+        match self {
+            TextureTarget::Rectangle => gl::TEXTURE_RECTANGLE,
+            TextureTarget::Texture2D => gl::TEXTURE_2D,
+        }
+    }
+}
+
+impl Default for TextureTarget {
+    fn default() -> TextureTarget {
+        TextureTarget::Rectangle
+    }
+}
+
+/// Options that control how a context is created.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextOptions {
+    /// If true, the context saves every piece of GL state it changes, both while it's being
+    /// created and while drawing, and restores it afterward. This makes it safe to drop into an
+    /// existing renderer that assumes its state is left alone, at the cost of some `glGet*()`
+    /// calls per draw. Defaults to false.
+    pub preserve_state: bool,
+    /// The body of a fragment shader to draw with in place of the default one, which simply
+    /// copies the texture. Defaults to `None`.
+    ///
+    /// The source must not contain a `#version` directive; the crate supplies one, followed by a
+    /// prelude that declares the texture as `uTexture` and defines two functions that work
+    /// regardless of the texture target: `vec4 sampleTexture(vec2 texCoord)`, which samples the
+    /// texture at coordinates normalized to `[0, 1]`, and `vec2 textureSizeF()`, which returns
+    /// the size of the texture in texels. For extra inputs bound with `Context::draw_multi()`,
+    /// the prelude also defines a `SAMPLER` macro naming the sampler type for the texture target,
+    /// along with `sampleAt(SAMPLER tex, vec2 texCoord)` and `samplerSizeF(SAMPLER tex)`. The
+    /// shader receives the normalized texture coordinate for the fragment in `vTexCoord` and
+    /// writes its output to `oFragColor`. For example, this shader swaps the red and blue
+    /// channels:
+    ///
+    /// ```glsl
+    /// in vec2 vTexCoord;
+    ///
+    /// out vec4 oFragColor;
+    ///
+    /// void main() {
+    ///     oFragColor = sampleTexture(vTexCoord).bgra;
+    /// }
+    /// ```
+    ///
+    /// To run under both desktop GL and GL ES, the shader must stick to the common subset of
+    /// GLSL 3.30 and GLSL ES 3.00. The crate declares default precisions of `highp` for ES.
+    ///
+    /// Under `Api::LegacyGl`, the shader is compiled as GLSL 1.20: `in` and `out` declarations at
+    /// the start of a line are rewritten, but the body must otherwise avoid newer features such as
+    /// `texture()` and integer `min()`/`max()`. GLSL 1.20 can't query texture sizes, so there's
+    /// no `samplerSizeF()`, and only the `sampleAt(SAMPLER tex, vec2 size, vec2 texCoord)` form
+    /// is available, which every API also defines. The crate fills in a `uniform vec2 uFooSize;`
+    /// declaration with the size of the texture bound to each extra sampler `uFoo`.
+    pub fragment_shader: Option<String>,
+    /// The API to draw with, which determines the GLSL dialect of the shaders. Defaults to
+    /// `None`, which uses the API chosen by `Capabilities::current()`.
+    pub api: Option<Api>,
+}
+
+impl Default for ContextOptions {
+    fn default() -> ContextOptions {
+        ContextOptions {
+            preserve_state: false,
+            fragment_shader: None,
+            api: None,
+        }
+    }
+}
+
+/// A transform applied to a quad in clip space, as a column-major 4x4 matrix.
+pub type Transform = [[f32; 4]; 4];
+
+/// The transform that leaves quads unchanged.
+pub const IDENTITY_TRANSFORM: Transform = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Options that control how a texture is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawOptions {
+    /// The type of the texture. Defaults to `TextureTarget::Rectangle`, which GL ES doesn't have;
+    /// use `Context::default_target()` for code that runs in either.
+    pub target: TextureTarget,
+    /// The rectangle of the texture to draw, in texels if measured in `Units::Pixels`. Defaults
+    /// to the entire texture.
+    pub src: Rect,
+    /// The rectangle of the viewport to draw into. Defaults to the entire viewport.
+    pub dest: Rect,
+    /// How the texture is flipped and rotated within `dest`. Defaults to upright.
+    pub orientation: Orientation,
+    /// How the texture is scaled to fit `dest`. Defaults to `FitMode::Stretch`.
+    pub fit: FitMode,
+    /// If set, `dest` is cleared to this color before drawing, so that the bars left uncovered
+    /// by `FitMode::Contain` and `FitMode::Center` have a defined color. `transform` isn't taken
+    /// into account. Defaults to `None`.
+    pub background: Option<[f32; 4]>,
+    /// A transform applied to the quad in clip space, after it's placed in `dest`. Defaults to
+    /// `IDENTITY_TRANSFORM`.
+    pub transform: Transform,
+    /// How to blend the quad with the contents of the framebuffer. If set, the mode is applied
+    /// for the draw and the previous blending state restored afterward. Defaults to `None`,
+    /// which leaves the blending state alone, so whatever the caller set up is used.
+    pub blend: Option<BlendMode>,
+    /// How to resample the texture. Defaults to `Filter::Native`.
+    pub filter: Filter,
+    /// How to convert the alpha representation of the texture as it's sampled. Defaults to
+    /// `AlphaConversion::Keep`.
+    pub alpha: AlphaConversion,
+}
+
+impl Default for DrawOptions {
+    fn default() -> DrawOptions {
+        DrawOptions {
+            target: TextureTarget::default(),
+            src: Rect::normalized(0.0, 0.0, 1.0, 1.0),
+            dest: Rect::normalized(0.0, 0.0, 1.0, 1.0),
+            orientation: Orientation::default(),
+            fit: FitMode::Stretch,
+            background: None,
+            transform: IDENTITY_TRANSFORM,
+            blend: None,
+            filter: Filter::Native,
+            alpha: AlphaConversion::Keep,
+        }
+    }
+}
+
+impl DrawOptions {
+    /// Returns true if drawing with these options depends on the size of the viewport.
+    fn needs_viewport(&self) -> bool {
+        self.dest.units == Units::Pixels ||
+            self.fit != FitMode::Stretch ||
+            self.background.is_some()
+    }
+}
+
+/// The values of the built-in shader uniforms for drawing a single quad, which are the same for
+/// every backend.
+struct QuadUniforms {
+    src_rect: [f32; 4],
+    src_in_texels: GLint,
+    orientation: [[f32; 2]; 2],
+    fit_mode: GLint,
+    dest_size: [f32; 2],
+    filter: GLint,
+    alpha_conversion: GLint,
+    dest_transform: [f32; 4],
+    transform: Transform,
+    /// The rectangle that `DrawOptions::background` clears, in window coordinates.
+    background_rect: [GLint; 4],
+}
+
+impl QuadUniforms {
+    /// Computes the uniforms for drawing with the given options into the given viewport, which
+    /// need only be accurate if `DrawOptions::needs_viewport()` is true. If `flip_y` is set, the
+    /// quad is flipped vertically in clip space, as when rendering into a `RenderTarget`.
+    fn new(options: &DrawOptions, viewport: [GLint; 4], flip_y: bool) -> QuadUniforms {
+        let (x, y) = (viewport[0] as f32, viewport[1] as f32);
+        let (width, height) = (viewport[2] as f32, viewport[3] as f32);
+        let dest = options.dest.to_normalized(|| (width, height));
+        let bottom = if flip_y { dest.y } else { 1.0 - dest.y - dest.height };
+
+        let mut transform = options.transform;
+        if flip_y {
+            for column in &mut transform {
+                column[1] = -column[1]
+            }
         }
+
+        QuadUniforms {
+            src_rect: [options.src.x, options.src.y, options.src.width, options.src.height],
+            src_in_texels: (options.src.units == Units::Pixels) as GLint,
+            orientation: options.orientation.matrix(),
+            fit_mode: match options.fit {
+                FitMode::Stretch => 0,
+                FitMode::Contain => 1,
+                FitMode::Cover => 2,
+                FitMode::Center => 3,
+            },
+            dest_size: [dest.width * width, dest.height * height],
+            filter: match options.filter {
+                Filter::Native => 0,
+                Filter::CatmullRom => 1,
+                Filter::Lanczos3 => 2,
+                Filter::Area => 3,
+            },
+            alpha_conversion: match options.alpha {
+                AlphaConversion::Keep => 0,
+                AlphaConversion::Premultiply => 1,
+                AlphaConversion::Unpremultiply => 2,
+            },
+            dest_transform: [
+                dest.width,
+                dest.height,
+                dest.x * 2.0 + dest.width - 1.0,
+                1.0 - dest.y * 2.0 - dest.height,
+            ],
+            transform: transform,
+            background_rect: [
+                (x + dest.x * width).round() as GLint,
+                (y + bottom * height).round() as GLint,
+                (dest.width * width).round() as GLint,
+                (dest.height * height).round() as GLint,
+            ],
+        }
+    }
+}
+
+/// How a quad is composited onto the framebuffer.
+///
+/// Except for `AlphaOverStraight`, these modes expect the shader to output premultiplied alpha.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Overwrites the framebuffer, ignoring alpha.
+    Replace,
+    /// Composites the quad over the framebuffer, treating its color as straight (not
+    /// premultiplied) alpha.
+    AlphaOverStraight,
+    /// Composites the quad over the framebuffer, treating its color as premultiplied alpha.
+    AlphaOverPremultiplied,
+    /// Adds the quad to the framebuffer.
+    Additive,
+    /// Multiplies the framebuffer by the quad.
+    Multiply,
+    /// Inverts both colors, multiplies them, and inverts the result, brightening the framebuffer.
+    Screen,
+}
+
+impl BlendMode {
+    /// Returns the source and destination blend factors for color and then alpha, with an
+    /// additive blend equation, or `None` if blending is disabled.
+    fn factors(self) -> Option<[GLenum; 4]> {
+        let (src_rgb, dest_rgb) = match self {
+            BlendMode::Replace => return None,
+            BlendMode::AlphaOverStraight => (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::AlphaOverPremultiplied => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (gl::ONE, gl::ONE),
+            BlendMode::Multiply => (gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => (gl::ONE, gl::ONE_MINUS_SRC_COLOR),
+        };
+        let (src_alpha, dest_alpha) = match self {
+            BlendMode::Additive => (gl::ONE, gl::ONE),
+            _ => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+        };
+        Some([src_rgb, dest_rgb, src_alpha, dest_alpha])
     }
 }
 
+/// How a texture is resampled as it's drawn.
+///
+/// The filters other than `Native` are implemented in the shader with `texelFetch()`, so they
+/// ignore the texture's filter parameters and work the same on texture rectangles, which can't
+/// have mipmaps. They cost considerably more per pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Samples the texture once, using its own `GL_TEXTURE_MIN_FILTER` and
+    /// `GL_TEXTURE_MAG_FILTER`.
+    Native,
+    /// Catmull-Rom bicubic interpolation over 4x4 texels. Sharp and cheap enough for upscaling
+    /// video.
+    CatmullRom,
+    /// Lanczos interpolation with three lobes, over 6x6 texels. Sharper than Catmull-Rom, with
+    /// slight ringing around hard edges.
+    Lanczos3,
+    /// Averages all the texels that each pixel covers. This is the filter to use for downscaling,
+    /// where the others alias. Reductions beyond 16 times along an axis are approximated by
+    /// skipping texels.
+    Area,
+}
+
+/// A conversion between straight and premultiplied alpha, applied in the shader as the texture is
+/// sampled.
+///
+/// Images decoded from files, such as PNGs, usually have straight alpha, while GPU-rendered
+/// content, including most `IOSurface`s, usually has premultiplied alpha. Converting to the
+/// representation that the blend mode expects makes both composite correctly: for example,
+/// `Premultiply` with `BlendMode::AlphaOverPremultiplied` for a PNG.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaConversion {
+    /// Leaves colors as they are.
+    Keep,
+    /// Multiplies colors by alpha, converting straight alpha to premultiplied alpha.
+    Premultiply,
+    /// Divides colors by alpha, converting premultiplied alpha to straight alpha.
+    Unpremultiply,
+}
+
+/// How a texture is scaled to fit its destination rectangle.
+///
+/// The aspect ratio of the source is taken from its size in texels, after orientation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitMode {
+    /// Stretches the texture to fill the destination, distorting it if the aspect ratios
+    /// differ.
+    Stretch,
+    /// Scales the texture to the largest size that fits entirely within the destination,
+    /// preserving its aspect ratio and centering it. This leaves letterbox bars if the aspect
+    /// ratios differ.
+    Contain,
+    /// Scales the texture to the smallest size that covers the whole destination, preserving its
+    /// aspect ratio and centering it. This crops the texture if the aspect ratios differ.
+    Cover,
+    /// Draws the texture at its native size, one texel per pixel, centered in the destination
+    /// and cropped if it's larger.
+    Center,
+}
+
+/// How a texture is flipped and rotated as it's drawn.
+///
+/// This is useful for textures whose origin isn't at the top left, such as the results of
+/// `glReadPixels()` and framebuffer objects (use `flip_y`) or camera frames captured sideways.
+/// Flips are applied first, then the rotation. Rotating by 90 or 270 degrees doesn't change the
+/// shape of the destination rectangle, so swap its width and height if necessary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Orientation {
+    /// Mirrors the texture horizontally.
+    pub flip_x: bool,
+    /// Mirrors the texture vertically.
+    pub flip_y: bool,
+    /// Rotates the texture clockwise.
+    pub rotation: Rotation,
+}
+
+impl Orientation {
+    /// Returns the column-major matrix that maps coordinates in the destination, relative to its
+    /// center, to coordinates in the texture, relative to its center.
+    fn matrix(&self) -> [[f32; 2]; 2] {
+        let rotation = match self.rotation {
+            Rotation::Rotate0 => [[1.0, 0.0], [0.0, 1.0]],
+            Rotation::Rotate90 => [[0.0, -1.0], [1.0, 0.0]],
+            Rotation::Rotate180 => [[-1.0, 0.0], [0.0, -1.0]],
+            Rotation::Rotate270 => [[0.0, 1.0], [-1.0, 0.0]],
+        };
+        let x = if self.flip_x { -1.0 } else { 1.0 };
+        let y = if self.flip_y { -1.0 } else { 1.0 };
+        [[rotation[0][0] * x, rotation[0][1] * y], [rotation[1][0] * x, rotation[1][1] * y]]
+    }
+}
+
+/// A clockwise rotation by a multiple of 90 degrees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Default for Rotation {
+    fn default() -> Rotation {
+        Rotation::Rotate0
+    }
+}
+
+/// An axis-aligned rectangle.
+///
+/// The origin is at the top left, and the Y axis points down, matching the orientation in which
+/// textures are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// The units that the other fields are measured in.
+    pub units: Units,
+}
+
+impl Rect {
+    /// Creates a rectangle measured in fractions of the whole, from 0.0 to 1.0.
+    pub fn normalized(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+            units: Units::Normalized,
+        }
+    }
+
+    /// Creates a rectangle measured in pixels.
+    pub fn pixels(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+            units: Units::Pixels,
+        }
+    }
+
+    /// Converts this rectangle to normalized units, calling `size` to find out the dimensions of
+    /// the whole in pixels if necessary.
+    fn to_normalized<F>(&self, size: F) -> Rect where F: FnOnce() -> (f32, f32) {
+        match self.units {
+            Units::Normalized => *self,
+            Units::Pixels => {
+                let (width, height) = size();
+                Rect::normalized(self.x / width,
+                                 self.y / height,
+                                 self.width / width,
+                                 self.height / height)
+            }
+        }
+    }
+}
+
+/// The units that a `Rect` is measured in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Units {
+    /// Fractions of the whole, from 0.0 to 1.0.
+    Normalized,
+    /// Pixels of the viewport, or texels of the texture for source rectangles.
+    Pixels,
+}
+
+/// Returns the current viewport, as `[x, y, width, height]` in window coordinates.
+fn viewport(gl: &Gl) -> [GLint; 4] {
+    let mut viewport: [GLint; 4] = [0; 4];
+    unsafe {
+        gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+    }
+    viewport
+}
+
+/// Returns the size of the current viewport in pixels.
+fn viewport_size(gl: &Gl) -> (f32, f32) {
+    let viewport = viewport(gl);
+    (viewport[2] as f32, viewport[3] as f32)
+}
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 struct Vertex {
@@ -159,32 +1170,38 @@ static VERTICES: [Vertex; 4] = [
     Vertex { x:  1.0, y: -1.0, u: 1.0, v: 1.0 },
 ];
 
-static VERTEX_SHADER: &'static str = r#"
-#version 330
-
-in vec2 aPosition;
-in vec2 aTexCoord;
-
-out vec2 vTexCoord;
-
-void main() {
-    vTexCoord = aTexCoord;
-    gl_Position = vec4(aPosition, 0.0, 1.0);
+/// Points the vertex attributes at a buffer of `Vertex`es bound to `GL_ARRAY_BUFFER`.
+unsafe fn set_quad_attributes(gl: &Gl) {
+    gl.VertexAttribPointer(POSITION_ATTRIBUTE,
+                           2,
+                           gl::FLOAT,
+                           gl::FALSE,
+                           mem::size_of::<Vertex>() as GLsizei,
+                           ptr::null());
+    gl.VertexAttribPointer(TEX_COORD_ATTRIBUTE,
+                           2,
+                           gl::FLOAT,
+                           gl::FALSE,
+                           mem::size_of::<Vertex>() as GLsizei,
+                           (mem::size_of::<f32>() * 2) as *const GLvoid);
+    gl.EnableVertexAttribArray(POSITION_ATTRIBUTE);
+    gl.EnableVertexAttribArray(TEX_COORD_ATTRIBUTE);
 }
-"#;
-
-static FRAGMENT_SHADER: &'static str = r#"
-#version 330
-
-uniform sampler2DRect uTexture;
 
-in vec2 vTexCoord;
-
-out vec4 oFragColor;
-
-void main() {
-    ivec2 size = textureSize(uTexture);
-    oFragColor = texture(uTexture, vTexCoord * vec2(float(size.x), float(size.y)));
+/// Points the vertex attributes at a buffer of `BatchVertex`es bound to `GL_ARRAY_BUFFER`.
+unsafe fn set_batch_attributes(gl: &Gl) {
+    gl.VertexAttribPointer(POSITION_ATTRIBUTE,
+                           4,
+                           gl::FLOAT,
+                           gl::FALSE,
+                           mem::size_of::<BatchVertex>() as GLsizei,
+                           ptr::null());
+    gl.VertexAttribPointer(TEX_COORD_ATTRIBUTE,
+                           4,
+                           gl::FLOAT,
+                           gl::FALSE,
+                           mem::size_of::<BatchVertex>() as GLsizei,
+                           (mem::size_of::<f32>() * 4) as *const GLvoid);
+    gl.EnableVertexAttribArray(POSITION_ATTRIBUTE);
+    gl.EnableVertexAttribArray(TEX_COORD_ATTRIBUTE);
 }
-"#;
-
@@ -0,0 +1,48 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loading GL function pointers.
+
+use gl;
+use std::ops::Deref;
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+/// The table of GL function pointers that the crate calls through.
+///
+/// Build one with `Gl::load_with()` from your windowing library's `get_proc_address()`, then pass
+/// it to `Context::new()` and to the constructors of `Texture` and `RenderTarget`. The crate never
+/// touches global function pointers, so it works alongside whatever bindings the rest of the
+/// application uses, and contexts built from different tables can live in the same process.
+///
+/// Clones are cheap and share the same table. `Gl` dereferences to the generated bindings, so the
+/// raw functions are available too: `gl.Viewport(0, 0, width, height)`.
+#[derive(Clone)]
+pub struct Gl(Rc<gl::Gl>);
+
+impl Gl {
+    /// Loads every GL function by name with the given closure, which should return a null
+    /// pointer for functions that aren't available.
+    ///
+    /// Function pointers may be specific to a GL context, so the context these are for should be
+    /// current while this is called, and whenever the table is used.
+    pub fn load_with<F>(get_proc_address: F) -> Gl where F: FnMut(&str) -> *const c_void {
+        Gl(Rc::new(gl::Gl::load_with(get_proc_address)))
+    }
+}
+
+impl Deref for Gl {
+    type Target = gl::Gl;
+
+    #[inline]
+    fn deref(&self) -> &gl::Gl {
+        &self.0
+    }
+}
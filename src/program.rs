@@ -0,0 +1,229 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shader compilation and linking.
+
+use {Api, Gl, TextureTarget};
+use error::{Error, ShaderStage};
+use shaders;
+use uniform::{self, ActiveUniform};
+
+use gl;
+use gl::types::{GLchar, GLenum, GLint, GLuint};
+use std::collections::HashMap;
+
+/// The attribute location that `aPosition` is bound to in every program.
+pub const POSITION_ATTRIBUTE: GLuint = 0;
+/// The attribute location that `aTexCoord` is bound to in every program.
+pub const TEX_COORD_ATTRIBUTE: GLuint = 1;
+
+/// A linked shader program, along with the shaders it was built from.
+///
+/// Attribute locations are fixed at link time, so every program with the same vertex layout can
+/// share the same vertex array.
+pub struct Program {
+    pub program: GLuint,
+    pub texture_uniform: GLint,
+    pub dest_transform_uniform: GLint,
+    pub transform_uniform: GLint,
+    pub src_rect_uniform: GLint,
+    pub src_in_texels_uniform: GLint,
+    pub orientation_uniform: GLint,
+    pub fit_mode_uniform: GLint,
+    pub dest_size_uniform: GLint,
+    pub alpha_conversion_uniform: GLint,
+    pub filter_uniform: GLint,
+    /// Every active uniform in the program, including the ones above.
+    pub uniforms: HashMap<String, ActiveUniform>,
+    vertex_shader: GLuint,
+    fragment_shader: GLuint,
+    gl: Gl,
+}
+
+impl Program {
+    /// Builds the program for drawing single quads of textures of the given type, with the given
+    /// fragment shader body.
+    pub unsafe fn for_target(gl: &Gl, api: Api, target: TextureTarget, fragment_body: &str)
+                             -> Result<Program, Error> {
+        Program::new(gl,
+                     &shaders::vertex_source(shaders::VERTEX_SHADER, target, api),
+                     &shaders::fragment_source(fragment_body, target, api))
+    }
+
+    /// Builds the program for drawing batches of textures of the given type, with the given
+    /// fragment shader body.
+    pub unsafe fn batch_for_target(gl: &Gl,
+                                   api: Api,
+                                   target: TextureTarget,
+                                   fragment_body: &str)
+                                   -> Result<Program, Error> {
+        Program::new(gl,
+                     &shaders::vertex_source(shaders::BATCH_VERTEX_SHADER, target, api),
+                     &shaders::fragment_source(fragment_body, target, api))
+    }
+
+    pub unsafe fn new(gl: &Gl, vertex_source: &str, fragment_source: &str)
+                      -> Result<Program, Error> {
+        let vertex_shader = compile_shader(gl, ShaderStage::Vertex, vertex_source)?;
+        let fragment_shader = match compile_shader(gl, ShaderStage::Fragment, fragment_source) {
+            Ok(fragment_shader) => fragment_shader,
+            Err(err) => {
+                gl.DeleteShader(vertex_shader);
+                return Err(err)
+            }
+        };
+
+        let program = match link_program(gl, vertex_shader, fragment_shader) {
+            Ok(program) => program,
+            Err(err) => {
+                gl.DeleteShader(fragment_shader);
+                gl.DeleteShader(vertex_shader);
+                return Err(err)
+            }
+        };
+
+        Ok(Program {
+            program: program,
+            texture_uniform: uniform_location(gl, program, "uTexture\0"),
+            dest_transform_uniform: uniform_location(gl, program, "uDestTransform\0"),
+            transform_uniform: uniform_location(gl, program, "uTransform\0"),
+            src_rect_uniform: uniform_location(gl, program, "uSrcRect\0"),
+            src_in_texels_uniform: uniform_location(gl, program, "uSrcInTexels\0"),
+            orientation_uniform: uniform_location(gl, program, "uOrientation\0"),
+            fit_mode_uniform: uniform_location(gl, program, "uFitMode\0"),
+            dest_size_uniform: uniform_location(gl, program, "uDestSize\0"),
+            alpha_conversion_uniform: uniform_location(gl, program, "uAlphaConversion\0"),
+            filter_uniform: uniform_location(gl, program, "uFilter\0"),
+            uniforms: uniform::active_uniforms(gl, program),
+            vertex_shader: vertex_shader,
+            fragment_shader: fragment_shader,
+            gl: gl.clone(),
+        })
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteProgram(self.program);
+            self.gl.DeleteShader(self.fragment_shader);
+            self.gl.DeleteShader(self.vertex_shader);
+        }
+    }
+}
+
+/// Looks up a uniform. The name must be NUL-terminated.
+pub unsafe fn uniform_location(gl: &Gl, program: GLuint, name: &str) -> GLint {
+    debug_assert!(name.ends_with('\0'));
+    gl.GetUniformLocation(program, name.as_ptr() as *const GLchar)
+}
+
+pub fn shader_type(stage: ShaderStage) -> GLenum {
+    match stage {
+        ShaderStage::Vertex => gl::VERTEX_SHADER,
+        ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+    }
+}
+
+unsafe fn compile_shader(gl: &Gl, stage: ShaderStage, source: &str) -> Result<GLuint, Error> {
+    let shader = gl.CreateShader(shader_type(stage));
+    gl.ShaderSource(shader,
+                    1,
+                    &(source.as_ptr() as *const GLchar),
+                    &(source.len() as GLint));
+    gl.CompileShader(shader);
+
+    let mut status = 0;
+    gl.GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status == gl::TRUE as GLint {
+        return Ok(shader)
+    }
+
+    let mut log_length = 0;
+    gl.GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_length);
+    let mut log = vec![0; log_length.max(0) as usize];
+    let mut written = 0;
+    gl.GetShaderInfoLog(shader, log_length, &mut written, log.as_mut_ptr() as *mut GLchar);
+    log.truncate(written.max(0) as usize);
+    gl.DeleteShader(shader);
+
+    Err(compilation_error(stage, String::from_utf8_lossy(&log).into_owned(), source))
+}
+
+/// Builds the error for a shader that failed to compile, finding the offending line of the
+/// source from the info log if possible.
+pub fn compilation_error(stage: ShaderStage, log: String, source: &str) -> Error {
+    let source_line = error_line_number(&log).and_then(|line| {
+        source.lines().nth(line.wrapping_sub(1)).map(|line| line.to_owned())
+    });
+    Error::ShaderCompilation {
+        stage: stage,
+        log: log,
+        source_line: source_line,
+    }
+}
+
+unsafe fn link_program(gl: &Gl, vertex_shader: GLuint, fragment_shader: GLuint)
+                       -> Result<GLuint, Error> {
+    let program = gl.CreateProgram();
+    gl.AttachShader(program, vertex_shader);
+    gl.AttachShader(program, fragment_shader);
+    gl.BindAttribLocation(program,
+                          POSITION_ATTRIBUTE,
+                          "aPosition\0".as_ptr() as *const GLchar);
+    gl.BindAttribLocation(program,
+                          TEX_COORD_ATTRIBUTE,
+                          "aTexCoord\0".as_ptr() as *const GLchar);
+    gl.LinkProgram(program);
+
+    let mut status = 0;
+    gl.GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    if status == gl::TRUE as GLint {
+        return Ok(program)
+    }
+
+    let mut log_length = 0;
+    gl.GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_length);
+    let mut log = vec![0; log_length.max(0) as usize];
+    let mut written = 0;
+    gl.GetProgramInfoLog(program, log_length, &mut written, log.as_mut_ptr() as *mut GLchar);
+    log.truncate(written.max(0) as usize);
+    gl.DeleteProgram(program);
+
+    Err(Error::ProgramLink {
+        log: String::from_utf8_lossy(&log).into_owned(),
+    })
+}
+
+/// Extracts the line number of the first error from a shader info log.
+///
+/// Drivers don't agree on a format, but they all lead with the source string index followed by
+/// the line number: Mesa writes `0:12(3): error: ...`, NVIDIA writes `0(12) : error ...`, and
+/// AMD and Apple write `ERROR: 0:12: ...`. Warnings can come first, so entries that don't mention
+/// an error are skipped.
+fn error_line_number(log: &str) -> Option<usize> {
+    for line in log.lines() {
+        let line = line.trim();
+        if !line.to_lowercase().contains("error") {
+            continue
+        }
+        let line = if line.starts_with("ERROR: ") { &line[7..] } else { line };
+        let digits = line.find(|c: char| !c.is_digit(10)).unwrap_or(line.len());
+        if digits == 0 || !(line[digits..].starts_with(':') || line[digits..].starts_with('(')) {
+            continue
+        }
+        let rest = &line[(digits + 1)..];
+        let number_length = rest.find(|c: char| !c.is_digit(10)).unwrap_or(rest.len());
+        if let Ok(number) = rest[..number_length].parse() {
+            return Some(number)
+        }
+    }
+    None
+}
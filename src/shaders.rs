@@ -0,0 +1,408 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! GLSL sources for the crate's programs.
+//!
+//! Shader bodies don't carry a `#version` directive; it's prepended when the full source is
+//! assembled, along with a prelude that abstracts over the texture target. The bodies are written
+//! in the common subset of GLSL 3.30 and GLSL ES 3.00, so the only differences between the two
+//! APIs are the directive and the default precision qualifiers that ES requires. For GLSL 1.20,
+//! `in` and `out` declarations are rewritten to `attribute` and `varying`, and since GLSL 1.20
+//! can't query texture sizes, each of the crate's samplers gets a size uniform that the crate
+//! fills in. Shaders query the dimensions of the texture in texels through `textureSizeF()`, and
+//! fragment shaders read it through `sampleTexture()`, which takes coordinates normalized to
+//! `[0, 1]` regardless of whether the texture is a rectangle or a 2D texture. `sampleTexture()`
+//! also applies the filter and alpha conversion requested for the draw. Additional samplers of the
+//! same target can be declared with the `SAMPLER` macro and read with `sampleAt()`, which takes
+//! the size of the sampler's texture explicitly.
+
+use {Api, TextureTarget};
+use error::ShaderStage;
+
+/// Assembles a complete vertex shader from a body for the given API, including the prelude for
+/// the given texture target.
+pub fn vertex_source(body: &str, target: TextureTarget, api: Api) -> String {
+    let mut source = String::from(version(api));
+    source.push_str(target_prelude(target, api));
+    push_sampler_sizes(&mut source, api);
+    source.push_str(COMMON_PRELUDE);
+    push_body(&mut source, body, ShaderStage::Vertex, api);
+    source
+}
+
+/// Assembles a complete fragment shader from a body for the given API, including the prelude for
+/// the given texture target.
+pub fn fragment_source(body: &str, target: TextureTarget, api: Api) -> String {
+    let mut source = String::from(version(api));
+    source.push_str(target_prelude(target, api));
+    push_sampler_sizes(&mut source, api);
+    source.push_str(COMMON_PRELUDE);
+    source.push_str(FRAGMENT_PRELUDE);
+    push_body(&mut source, body, ShaderStage::Fragment, api);
+    source
+}
+
+/// Returns the name of the uniform that holds the size of the named sampler's texture under
+/// GLSL 1.20.
+pub fn size_uniform_name(sampler: &str) -> String {
+    format!("{}Size", sampler)
+}
+
+/// Defines the sizes of the crate's own samplers under the names given by `size_uniform_name()`:
+/// as uniforms for GLSL 1.20, and as macros for the size queries otherwise.
+fn push_sampler_sizes(source: &mut String, api: Api) {
+    for sampler in SIZED_SAMPLERS {
+        let size = size_uniform_name(sampler);
+        if api == Api::LegacyGl {
+            source.push_str(&format!("uniform vec2 {};\n", size));
+        } else {
+            source.push_str(&format!("#define {} samplerSizeF({})\n", size, sampler));
+        }
+    }
+}
+
+/// Appends a shader body, rewriting its `in` and `out` declarations for GLSL 1.20 if necessary.
+///
+/// Only declarations at the start of a line are rewritten. A fragment shader's output becomes a
+/// macro for `gl_FragColor`, so the line count, and with it the line numbers in error messages,
+/// stays the same.
+fn push_body(source: &mut String, body: &str, stage: ShaderStage, api: Api) {
+    if api != Api::LegacyGl {
+        source.push_str(body);
+        return
+    }
+
+    for line in body.lines() {
+        let declaration = line.trim();
+        if declaration.starts_with("in ") {
+            source.push_str(match stage {
+                ShaderStage::Vertex => "attribute ",
+                ShaderStage::Fragment => "varying ",
+            });
+            source.push_str(&declaration[3..]);
+        } else if declaration.starts_with("out ") && stage == ShaderStage::Vertex {
+            source.push_str("varying ");
+            source.push_str(&declaration[4..]);
+        } else if declaration.starts_with("out ") {
+            let name = declaration.trim_matches(';').split_whitespace().last().unwrap_or("");
+            source.push_str("#define ");
+            source.push_str(name);
+            source.push_str(" gl_FragColor");
+        } else {
+            source.push_str(line);
+        }
+        source.push('\n');
+    }
+}
+
+fn version(api: Api) -> &'static str {
+    match api {
+        Api::Gl => GL_VERSION,
+        Api::Gles => GLES_VERSION,
+        Api::LegacyGl => LEGACY_GL_VERSION,
+    }
+}
+
+fn target_prelude(target: TextureTarget, api: Api) -> &'static str {
+    match (target, api) {
+        (TextureTarget::Rectangle, Api::LegacyGl) => LEGACY_RECTANGLE_PRELUDE,
+        (TextureTarget::Texture2D, Api::LegacyGl) => LEGACY_TEXTURE_2D_PRELUDE,
+        (TextureTarget::Rectangle, _) => RECTANGLE_PRELUDE,
+        (TextureTarget::Texture2D, _) => TEXTURE_2D_PRELUDE,
+    }
+}
+
+/// The luma or RGB texture, followed by the chroma planes of YUV images.
+static SIZED_SAMPLERS: &'static [&'static str] = &["uTexture", "uTextureU", "uTextureV"];
+
+static GL_VERSION: &'static str = "#version 330\n";
+
+// Uniforms shared between stages must agree on precision, so both stages use the same defaults.
+static GLES_VERSION: &'static str = r#"#version 300 es
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+"#;
+
+static LEGACY_GL_VERSION: &'static str = "#version 120\n";
+
+static RECTANGLE_PRELUDE: &'static str = r#"
+#define SAMPLER sampler2DRect
+
+vec2 samplerSizeF(SAMPLER tex) {
+    ivec2 size = textureSize(tex);
+    return vec2(float(size.x), float(size.y));
+}
+
+vec4 sampleAt(SAMPLER tex, vec2 size, vec2 texCoord) {
+    return texture(tex, texCoord * size);
+}
+
+vec4 sampleAt(SAMPLER tex, vec2 texCoord) {
+    return sampleAt(tex, samplerSizeF(tex), texCoord);
+}
+
+// Fetches a single texel, clamping to the edge.
+vec4 fetchAt(SAMPLER tex, vec2 size, ivec2 texel) {
+    return texelFetch(tex, clamp(texel, ivec2(0), ivec2(size) - 1));
+}
+"#;
+
+static TEXTURE_2D_PRELUDE: &'static str = r#"
+#define SAMPLER sampler2D
+
+vec2 samplerSizeF(SAMPLER tex) {
+    ivec2 size = textureSize(tex, 0);
+    return vec2(float(size.x), float(size.y));
+}
+
+vec4 sampleAt(SAMPLER tex, vec2 size, vec2 texCoord) {
+    return texture(tex, texCoord);
+}
+
+vec4 sampleAt(SAMPLER tex, vec2 texCoord) {
+    return texture(tex, texCoord);
+}
+
+// Fetches a single texel, clamping to the edge.
+vec4 fetchAt(SAMPLER tex, vec2 size, ivec2 texel) {
+    return texelFetch(tex, clamp(texel, ivec2(0), ivec2(size) - 1), 0);
+}
+"#;
+
+// GLSL 1.20 can't query the size of a texture, so there's no `samplerSizeF()`, and the helpers
+// only take the size explicitly.
+static LEGACY_RECTANGLE_PRELUDE: &'static str = r#"
+#extension GL_ARB_texture_rectangle : enable
+#define SAMPLER sampler2DRect
+
+vec4 sampleAt(SAMPLER tex, vec2 size, vec2 texCoord) {
+    return texture2DRect(tex, texCoord * size);
+}
+
+// Fetches a single texel, clamping to the edge.
+vec4 fetchAt(SAMPLER tex, vec2 size, ivec2 texel) {
+    return texture2DRect(tex, clamp(vec2(texel), vec2(0.0), size - 1.0) + 0.5);
+}
+"#;
+
+static LEGACY_TEXTURE_2D_PRELUDE: &'static str = r#"
+#define SAMPLER sampler2D
+
+vec4 sampleAt(SAMPLER tex, vec2 size, vec2 texCoord) {
+    return texture2D(tex, texCoord);
+}
+
+// Fetches a single texel, clamping to the edge.
+vec4 fetchAt(SAMPLER tex, vec2 size, ivec2 texel) {
+    return texture2D(tex, (clamp(vec2(texel), vec2(0.0), size - 1.0) + 0.5) / size);
+}
+"#;
+
+static COMMON_PRELUDE: &'static str = r#"
+uniform SAMPLER uTexture;
+
+vec2 textureSizeF() {
+    return uTextureSize;
+}
+"#;
+
+static FRAGMENT_PRELUDE: &'static str = r#"
+// 0 leaves colors alone, 1 premultiplies them by alpha, and 2 divides them by alpha.
+uniform int uAlphaConversion;
+// 0 uses the texture's own filter, 1 is Catmull-Rom, 2 is Lanczos-3, and 3 is area averaging.
+uniform int uFilter;
+
+#define PI 3.14159265358979
+// Beyond this many texels per pixel on an axis, area averaging skips texels.
+#define MAX_AREA_TAPS 16
+
+float catmullRomWeight(float x) {
+    x = abs(x);
+    if (x < 1.0)
+        return (1.5 * x - 2.5) * x * x + 1.0;
+    if (x < 2.0)
+        return ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0;
+    return 0.0;
+}
+
+float lanczos3Weight(float x) {
+    x = abs(x);
+    if (x < 1e-5)
+        return 1.0;
+    if (x >= 3.0)
+        return 0.0;
+    return 3.0 * sin(PI * x) * sin(PI * x / 3.0) / (PI * PI * x * x);
+}
+
+// Convolves the texels around `texel` with a separable kernel of the given radius. `texel` is in
+// unnormalized coordinates, with texel centers at half-integers.
+vec4 sampleKernel(vec2 texel, int radius) {
+    vec2 position = texel - 0.5;
+    ivec2 base = ivec2(floor(position));
+    vec2 fraction = position - floor(position);
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    for (int y = 1 - radius; y <= radius; y++) {
+        for (int x = 1 - radius; x <= radius; x++) {
+            vec2 offset = vec2(float(x), float(y)) - fraction;
+            float weight = radius == 2 ?
+                catmullRomWeight(offset.x) * catmullRomWeight(offset.y) :
+                lanczos3Weight(offset.x) * lanczos3Weight(offset.y);
+            sum += weight * fetchAt(uTexture, uTextureSize, base + ivec2(x, y));
+            total += weight;
+        }
+    }
+    return sum / total;
+}
+
+// Averages the texels under a box of the given size centered on `texel`, weighting each by how
+// much of it the box covers.
+vec4 sampleArea(vec2 texel, vec2 footprint) {
+    vec2 lo = texel - 0.5 * max(footprint, vec2(1.0));
+    vec2 hi = texel + 0.5 * max(footprint, vec2(1.0));
+    ivec2 first = ivec2(floor(lo)), last = ivec2(ceil(hi)) - 1;
+    ivec2 stride = ivec2(max(vec2((last - first) / MAX_AREA_TAPS + 1), vec2(1.0)));
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    for (int y = first.y; y <= last.y; y += stride.y) {
+        float height = min(hi.y, float(y + stride.y)) - max(lo.y, float(y));
+        for (int x = first.x; x <= last.x; x += stride.x) {
+            float width = min(hi.x, float(x + stride.x)) - max(lo.x, float(x));
+            sum += width * height * fetchAt(uTexture, uTextureSize, ivec2(x, y) + stride / 2);
+            total += width * height;
+        }
+    }
+    return sum / total;
+}
+
+vec4 sampleTexture(vec2 texCoord) {
+    vec4 color;
+    if (uFilter == 0) {
+        color = sampleAt(uTexture, uTextureSize, texCoord);
+    } else {
+        vec2 texel = texCoord * textureSizeF();
+        if (uFilter == 1) {
+            color = sampleKernel(texel, 2);
+        } else if (uFilter == 2) {
+            color = sampleKernel(texel, 3);
+        } else {
+            vec2 footprint = abs(dFdx(texel)) + abs(dFdy(texel));
+            color = sampleArea(texel, footprint);
+        }
+    }
+
+    if (uAlphaConversion == 1)
+        color.rgb *= color.a;
+    else if (uAlphaConversion == 2)
+        color.rgb = color.a > 0.0 ? color.rgb / color.a : vec3(0.0);
+    return color;
+}
+"#;
+
+pub static VERTEX_SHADER: &'static str = r#"
+// Scale in `xy`, translation in `zw`, applied to the full-viewport quad.
+uniform vec4 uDestTransform;
+// Applied in clip space after the quad is placed in its destination rectangle.
+uniform mat4 uTransform;
+// Origin in `xy`, size in `zw`. Measured in texels if `uSrcInTexels` is set, and normalized
+// otherwise.
+uniform vec4 uSrcRect;
+uniform bool uSrcInTexels;
+// Flips and rotates texture coordinates about the center of the quad.
+uniform mat2 uOrientation;
+// 0 stretches, 1 contains, 2 covers, and 3 centers at 1:1.
+uniform int uFitMode;
+// The size of the destination rectangle in pixels. Only set when fitting.
+uniform vec2 uDestSize;
+
+in vec2 aPosition;
+in vec2 aTexCoord;
+
+out vec2 vTexCoord;
+
+void main() {
+    // The size of the source rectangle in texels, as it appears after orientation.
+    vec2 srcSize = uSrcInTexels ? uSrcRect.zw : uSrcRect.zw * textureSizeF();
+    srcSize = abs(uOrientation * srcSize);
+
+    // The fraction of the destination that the quad covers, and the fraction of the source that
+    // it shows.
+    vec2 quadScale = vec2(1.0), srcScale = vec2(1.0);
+    if (uFitMode == 1) {
+        vec2 ratio = uDestSize / srcSize;
+        quadScale = min(ratio.x, ratio.y) / ratio;
+    } else if (uFitMode == 2) {
+        vec2 ratio = uDestSize / srcSize;
+        srcScale = ratio / max(ratio.x, ratio.y);
+    } else if (uFitMode == 3) {
+        quadScale = min(srcSize / uDestSize, 1.0);
+        srcScale = min(uDestSize / srcSize, 1.0);
+    }
+
+    vec2 corner = uOrientation * ((aTexCoord - 0.5) * srcScale) + 0.5;
+    vec2 texCoord = uSrcRect.xy + corner * uSrcRect.zw;
+    vTexCoord = uSrcInTexels ? texCoord / textureSizeF() : texCoord;
+
+    vec2 position = aPosition * quadScale * uDestTransform.xy + uDestTransform.zw;
+    gl_Position = uTransform * vec4(position, 0.0, 1.0);
+}
+"#;
+
+pub static BATCH_VERTEX_SHADER: &'static str = r#"
+// Clip space, with any transform already applied.
+in vec4 aPosition;
+// Normalized coordinates in `xy`, plus texel coordinates in `zw`.
+in vec4 aTexCoord;
+
+out vec2 vTexCoord;
+
+void main() {
+    vTexCoord = aTexCoord.xy + aTexCoord.zw / textureSizeF();
+    gl_Position = aPosition;
+}
+"#;
+
+pub static FRAGMENT_SHADER: &'static str = r#"
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+void main() {
+    oFragColor = sampleTexture(vTexCoord);
+}
+"#;
+
+pub static YUV_FRAGMENT_SHADER: &'static str = r#"
+// `uTexture` holds the luma plane. For I420, `uTextureU` and `uTextureV` hold the chroma planes;
+// for NV12, `uTextureU` holds both, interleaved.
+uniform SAMPLER uTextureU;
+uniform SAMPLER uTextureV;
+uniform bool uInterleavedChroma;
+// Converts from offset YCbCr to RGB, including range expansion.
+uniform mat3 uYuvMatrix;
+uniform vec3 uYuvOffset;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+void main() {
+    float y = sampleTexture(vTexCoord).r;
+    vec2 uv;
+    if (uInterleavedChroma) {
+        uv = sampleAt(uTextureU, uTextureUSize, vTexCoord).rg;
+    } else {
+        uv = vec2(sampleAt(uTextureU, uTextureUSize, vTexCoord).r,
+                  sampleAt(uTextureV, uTextureVSize, vTexCoord).r);
+    }
+    oFragColor = vec4(clamp(uYuvMatrix * (vec3(y, uv) - uYuvOffset), 0.0, 1.0), 1.0);
+}
+"#;
@@ -0,0 +1,326 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Saving and restoring the caller's GL state.
+
+use {Api, BlendMode, Capabilities, Gl};
+
+use gl;
+use gl::types::{GLboolean, GLenum, GLint, GLsizei, GLuint, GLvoid};
+use program::{POSITION_ATTRIBUTE, TEX_COORD_ATTRIBUTE};
+use std::ptr;
+
+/// The GL state that the crate modifies while creating contexts and drawing.
+pub struct SavedState {
+    program: GLuint,
+    /// `None` in legacy GL, which may not have vertex array objects.
+    vertex_array: Option<GLuint>,
+    /// The vertex attributes we point at our buffers, which only need saving in legacy GL, where
+    /// they aren't contained in our own vertex array objects.
+    vertex_attributes: Vec<VertexAttribute>,
+    array_buffer: GLuint,
+    active_texture: GLenum,
+    /// The rectangle and 2D texture bindings of each texture unit we touch, starting from
+    /// `GL_TEXTURE0`. The rectangle binding is `None` if the context doesn't have rectangle
+    /// textures.
+    textures: Vec<(Option<GLuint>, GLuint)>,
+    gl: Gl,
+}
+
+impl SavedState {
+    /// Saves the current state, including the texture bindings of the first `texture_units`
+    /// texture units.
+    pub unsafe fn save(gl: &Gl, capabilities: &Capabilities, texture_units: usize)
+                       -> SavedState {
+        let active_texture = get_integer(gl, gl::ACTIVE_TEXTURE) as GLenum;
+        let textures = (0..texture_units).map(|unit| {
+            gl.ActiveTexture(gl::TEXTURE0 + unit as GLenum);
+            let texture_rectangle = if capabilities.rectangle_textures {
+                Some(get_integer(gl, gl::TEXTURE_BINDING_RECTANGLE) as GLuint)
+            } else {
+                None
+            };
+            (texture_rectangle, get_integer(gl, gl::TEXTURE_BINDING_2D) as GLuint)
+        }).collect();
+        gl.ActiveTexture(active_texture);
+        let (vertex_array, vertex_attributes) = match capabilities.api {
+            Api::Gl | Api::Gles => {
+                (Some(get_integer(gl, gl::VERTEX_ARRAY_BINDING) as GLuint), vec![])
+            }
+            Api::LegacyGl => {
+                let indices = [POSITION_ATTRIBUTE, TEX_COORD_ATTRIBUTE];
+                let vertex_attributes = indices.iter().map(|&index| {
+                    VertexAttribute::save(gl, capabilities, index)
+                }).collect();
+                (None, vertex_attributes)
+            }
+        };
+        SavedState {
+            program: get_integer(gl, gl::CURRENT_PROGRAM) as GLuint,
+            vertex_array: vertex_array,
+            vertex_attributes: vertex_attributes,
+            array_buffer: get_integer(gl, gl::ARRAY_BUFFER_BINDING) as GLuint,
+            active_texture: active_texture,
+            textures: textures,
+            gl: gl.clone(),
+        }
+    }
+
+    pub unsafe fn restore(&self) {
+        let gl = &self.gl;
+        if let Some(vertex_array) = self.vertex_array {
+            gl.BindVertexArray(vertex_array);
+        }
+        for vertex_attribute in &self.vertex_attributes {
+            vertex_attribute.restore(gl);
+        }
+        gl.BindBuffer(gl::ARRAY_BUFFER, self.array_buffer);
+        gl.UseProgram(self.program);
+        for (unit, &(texture_rectangle, texture_2d)) in self.textures.iter().enumerate() {
+            gl.ActiveTexture(gl::TEXTURE0 + unit as GLenum);
+            if let Some(texture_rectangle) = texture_rectangle {
+                gl.BindTexture(gl::TEXTURE_RECTANGLE, texture_rectangle);
+            }
+            gl.BindTexture(gl::TEXTURE_2D, texture_2d);
+        }
+        gl.ActiveTexture(self.active_texture);
+    }
+}
+
+/// The state of a generic vertex attribute array outside of any vertex array object.
+struct VertexAttribute {
+    index: GLuint,
+    enabled: bool,
+    size: GLint,
+    data_type: GLenum,
+    normalized: GLboolean,
+    /// Whether the attribute was set with `glVertexAttribIPointer()`, which needs GL 3.0.
+    integer: bool,
+    stride: GLsizei,
+    pointer: *mut GLvoid,
+    buffer: GLuint,
+}
+
+impl VertexAttribute {
+    unsafe fn save(gl: &Gl, capabilities: &Capabilities, index: GLuint) -> VertexAttribute {
+        let get = |name| {
+            let mut value = 0;
+            gl.GetVertexAttribiv(index, name, &mut value);
+            value
+        };
+        let mut pointer = ptr::null_mut();
+        gl.GetVertexAttribPointerv(index, gl::VERTEX_ATTRIB_ARRAY_POINTER, &mut pointer);
+        VertexAttribute {
+            index: index,
+            enabled: get(gl::VERTEX_ATTRIB_ARRAY_ENABLED) != 0,
+            size: get(gl::VERTEX_ATTRIB_ARRAY_SIZE),
+            data_type: get(gl::VERTEX_ATTRIB_ARRAY_TYPE) as GLenum,
+            normalized: get(gl::VERTEX_ATTRIB_ARRAY_NORMALIZED) as GLboolean,
+            integer: capabilities.version >= (3, 0) && get(gl::VERTEX_ATTRIB_ARRAY_INTEGER) != 0,
+            stride: get(gl::VERTEX_ATTRIB_ARRAY_STRIDE),
+            pointer: pointer,
+            buffer: get(gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING) as GLuint,
+        }
+    }
+
+    /// Restores the attribute, leaving its buffer bound to `GL_ARRAY_BUFFER`.
+    unsafe fn restore(&self, gl: &Gl) {
+        gl.BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+        if self.integer {
+            gl.VertexAttribIPointer(self.index,
+                                    self.size,
+                                    self.data_type,
+                                    self.stride,
+                                    self.pointer);
+        } else {
+            gl.VertexAttribPointer(self.index,
+                                   self.size,
+                                   self.data_type,
+                                   self.normalized,
+                                   self.stride,
+                                   self.pointer);
+        }
+        if self.enabled {
+            gl.EnableVertexAttribArray(self.index)
+        } else {
+            gl.DisableVertexAttribArray(self.index)
+        }
+    }
+}
+
+/// Restores the saved state, if any, when dropped.
+pub struct StateGuard(Option<SavedState>);
+
+impl StateGuard {
+    /// Saves the current state, including the first `texture_units` texture units, if
+    /// `preserve` is true; otherwise, does nothing.
+    pub fn new(gl: &Gl, preserve: bool, capabilities: &Capabilities, texture_units: usize)
+               -> StateGuard {
+        if !preserve {
+            return StateGuard(None)
+        }
+        StateGuard(Some(unsafe { SavedState::save(gl, capabilities, texture_units) }))
+    }
+}
+
+impl Drop for StateGuard {
+    fn drop(&mut self) {
+        if let Some(ref state) = self.0 {
+            unsafe {
+                state.restore()
+            }
+        }
+    }
+}
+
+/// The few GL calls needed to save, change, and restore the blending and clearing state, so that
+/// the same logic serves both `Gl` and other bindings such as `glow`.
+pub trait StateFunctions {
+    unsafe fn is_enabled(&self, capability: GLenum) -> bool;
+    unsafe fn set_enabled(&self, capability: GLenum, enabled: bool);
+    unsafe fn get_integers(&self, name: GLenum, values: &mut [GLint]);
+    unsafe fn get_floats(&self, name: GLenum, values: &mut [f32]);
+    /// Sets the color and alpha blend equations and then the source and destination blend
+    /// factors for color and alpha.
+    unsafe fn set_blend(&self, equations: [GLenum; 2], factors: [GLenum; 4]);
+    unsafe fn set_scissor(&self, rect: [GLint; 4]);
+    unsafe fn set_clear_color(&self, color: [f32; 4]);
+    unsafe fn clear_color_buffer(&self);
+}
+
+impl StateFunctions for Gl {
+    unsafe fn is_enabled(&self, capability: GLenum) -> bool {
+        self.IsEnabled(capability) == gl::TRUE
+    }
+
+    unsafe fn set_enabled(&self, capability: GLenum, enabled: bool) {
+        if enabled {
+            self.Enable(capability)
+        } else {
+            self.Disable(capability)
+        }
+    }
+
+    unsafe fn get_integers(&self, name: GLenum, values: &mut [GLint]) {
+        self.GetIntegerv(name, values.as_mut_ptr())
+    }
+
+    unsafe fn get_floats(&self, name: GLenum, values: &mut [f32]) {
+        self.GetFloatv(name, values.as_mut_ptr())
+    }
+
+    unsafe fn set_blend(&self, equations: [GLenum; 2], factors: [GLenum; 4]) {
+        self.BlendEquationSeparate(equations[0], equations[1]);
+        self.BlendFuncSeparate(factors[0], factors[1], factors[2], factors[3]);
+    }
+
+    unsafe fn set_scissor(&self, rect: [GLint; 4]) {
+        self.Scissor(rect[0], rect[1], rect[2], rect[3])
+    }
+
+    unsafe fn set_clear_color(&self, color: [f32; 4]) {
+        self.ClearColor(color[0], color[1], color[2], color[3])
+    }
+
+    unsafe fn clear_color_buffer(&self) {
+        self.Clear(gl::COLOR_BUFFER_BIT)
+    }
+}
+
+/// The blending state of the pipeline.
+struct BlendState {
+    enabled: bool,
+    equations: [GLenum; 2],
+    factors: [GLenum; 4],
+}
+
+impl BlendState {
+    unsafe fn save<G>(gl: &G) -> BlendState where G: StateFunctions + ?Sized {
+        let get = |name| {
+            let mut value = [0];
+            gl.get_integers(name, &mut value);
+            value[0] as GLenum
+        };
+        BlendState {
+            enabled: gl.is_enabled(gl::BLEND),
+            equations: [get(gl::BLEND_EQUATION_RGB), get(gl::BLEND_EQUATION_ALPHA)],
+            factors: [
+                get(gl::BLEND_SRC_RGB),
+                get(gl::BLEND_DST_RGB),
+                get(gl::BLEND_SRC_ALPHA),
+                get(gl::BLEND_DST_ALPHA),
+            ],
+        }
+    }
+
+    unsafe fn restore<G>(&self, gl: &G) where G: StateFunctions + ?Sized {
+        gl.set_enabled(gl::BLEND, self.enabled);
+        gl.set_blend(self.equations, self.factors);
+    }
+}
+
+/// Applies a blend mode, restoring the previous blending state when dropped.
+pub struct BlendGuard<'a, G> where G: 'a + StateFunctions + ?Sized {
+    state: BlendState,
+    gl: &'a G,
+}
+
+impl<'a, G> BlendGuard<'a, G> where G: 'a + StateFunctions + ?Sized {
+    pub fn new(gl: &'a G, mode: BlendMode) -> BlendGuard<'a, G> {
+        unsafe {
+            let state = BlendState::save(gl);
+            match mode.factors() {
+                Some(factors) => {
+                    gl.set_enabled(gl::BLEND, true);
+                    gl.set_blend([gl::FUNC_ADD, gl::FUNC_ADD], factors);
+                }
+                None => gl.set_enabled(gl::BLEND, false),
+            }
+            BlendGuard {
+                state: state,
+                gl: gl,
+            }
+        }
+    }
+}
+
+impl<'a, G> Drop for BlendGuard<'a, G> where G: 'a + StateFunctions + ?Sized {
+    fn drop(&mut self) {
+        unsafe {
+            self.state.restore(self.gl)
+        }
+    }
+}
+
+/// Clears the given rectangle of the framebuffer, in window coordinates, to the given color,
+/// leaving the scissor and clear color state as it was.
+pub unsafe fn clear_rect<G>(gl: &G, rect: [GLint; 4], color: [f32; 4])
+                            where G: StateFunctions + ?Sized {
+    let scissor_test = gl.is_enabled(gl::SCISSOR_TEST);
+    let mut scissor_box = [0; 4];
+    gl.get_integers(gl::SCISSOR_BOX, &mut scissor_box);
+    let mut clear_color = [0.0; 4];
+    gl.get_floats(gl::COLOR_CLEAR_VALUE, &mut clear_color);
+
+    gl.set_enabled(gl::SCISSOR_TEST, true);
+    gl.set_scissor(rect);
+    gl.set_clear_color(color);
+    gl.clear_color_buffer();
+
+    gl.set_clear_color(clear_color);
+    gl.set_scissor(scissor_box);
+    gl.set_enabled(gl::SCISSOR_TEST, scissor_test);
+}
+
+unsafe fn get_integer(gl: &Gl, name: GLenum) -> GLint {
+    let mut value = 0;
+    gl.GetIntegerv(name, &mut value);
+    value
+}
@@ -0,0 +1,213 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Offscreen framebuffers to draw into.
+
+use {Error, Gl, TextureTarget};
+
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use std::os::raw::c_void;
+use std::ptr;
+
+/// A framebuffer object with a color texture attached, for drawing offscreen.
+///
+/// Draw into it with `Context::render_to()`, then use `texture()` as the input of another draw.
+/// This makes it easy to build multi-pass pipelines, such as a blur followed by tone mapping.
+/// Contents are stored top-down, like every other texture the crate draws, so the result can be
+/// drawn with the default orientation.
+///
+/// The framebuffer and texture are deleted when this is dropped, so the GL context it was created
+/// in must be current at that time.
+pub struct RenderTarget {
+    framebuffer: GLuint,
+    texture: GLuint,
+    target: TextureTarget,
+    width: u32,
+    height: u32,
+    gl: Gl,
+}
+
+impl RenderTarget {
+    /// Creates a render target with an 8-bit RGBA color texture of the given size and type.
+    ///
+    /// You must have a current valid GL context before calling this.
+    pub fn new(gl: &Gl, width: u32, height: u32, target: TextureTarget)
+               -> Result<RenderTarget, Error> {
+        RenderTarget::with_format(gl, width, height, target, gl::RGBA8)
+    }
+
+    /// Creates a render target with a color texture of the given size, type, and sized internal
+    /// format, such as `gl::RGBA16F` for high-dynamic-range intermediate results. (GL ES can only
+    /// render to floating-point formats with `GL_EXT_color_buffer_float`.)
+    ///
+    /// You must have a current valid GL context before calling this.
+    pub fn with_format(gl: &Gl,
+                       width: u32,
+                       height: u32,
+                       target: TextureTarget,
+                       internal_format: GLenum)
+                       -> Result<RenderTarget, Error> {
+        unsafe {
+            let mut old_framebuffer = 0;
+            gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut old_framebuffer);
+            let mut old_texture = 0;
+            gl.GetIntegerv(match target {
+                TextureTarget::Rectangle => gl::TEXTURE_BINDING_RECTANGLE,
+                TextureTarget::Texture2D => gl::TEXTURE_BINDING_2D,
+            }, &mut old_texture);
+
+            // GL ES requires the format and type to match the internal format, even though
+            // there are no pixels to transfer.
+            let (format, gl_type) = transfer_format(internal_format);
+            let mut texture = 0;
+            gl.GenTextures(1, &mut texture);
+            gl.BindTexture(target.gl_target(), texture);
+            gl.TexImage2D(target.gl_target(),
+                          0,
+                          internal_format as GLint,
+                          width as GLsizei,
+                          height as GLsizei,
+                          0,
+                          format,
+                          gl_type,
+                          ptr::null());
+            gl.TexParameteri(target.gl_target(), gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(target.gl_target(), gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl.TexParameteri(target.gl_target(),
+                             gl::TEXTURE_WRAP_S,
+                             gl::CLAMP_TO_EDGE as GLint);
+            gl.TexParameteri(target.gl_target(),
+                             gl::TEXTURE_WRAP_T,
+                             gl::CLAMP_TO_EDGE as GLint);
+
+            let mut framebuffer = 0;
+            gl.GenFramebuffers(1, &mut framebuffer);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER,
+                                    gl::COLOR_ATTACHMENT0,
+                                    target.gl_target(),
+                                    texture,
+                                    0);
+            let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, old_framebuffer as GLuint);
+            gl.BindTexture(target.gl_target(), old_texture as GLuint);
+
+            let render_target = RenderTarget {
+                framebuffer: framebuffer,
+                texture: texture,
+                target: target,
+                width: width,
+                height: height,
+                gl: gl.clone(),
+            };
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(Error::IncompleteFramebuffer { status: status })
+            }
+            Ok(render_target)
+        }
+    }
+
+    /// Returns the color texture, for drawing the contents of this target.
+    #[inline]
+    pub fn texture(&self) -> GLuint {
+        self.texture
+    }
+
+    /// Returns the type of the color texture.
+    #[inline]
+    pub fn target(&self) -> TextureTarget {
+        self.target
+    }
+
+    /// Returns the framebuffer object, for binding it yourself.
+    #[inline]
+    pub fn framebuffer(&self) -> GLuint {
+        self.framebuffer
+    }
+
+    /// Returns the width and height of the target in pixels.
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Reads back the contents of the target as 8-bit RGBA, with rows ordered from top to bottom
+    /// and no padding between them.
+    ///
+    /// This stalls until all drawing into the target has finished, so it's mostly useful for
+    /// tests, screenshots, and headless rendering.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let mut pixels = vec![0; self.width as usize * self.height as usize * 4];
+        let gl = &self.gl;
+        unsafe {
+            let mut old_framebuffer = 0;
+            gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut old_framebuffer);
+            let mut old_alignment = 0;
+            gl.GetIntegerv(gl::PACK_ALIGNMENT, &mut old_alignment);
+
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(0,
+                          0,
+                          self.width as GLsizei,
+                          self.height as GLsizei,
+                          gl::RGBA,
+                          gl::UNSIGNED_BYTE,
+                          pixels.as_mut_ptr() as *mut c_void);
+
+            gl.PixelStorei(gl::PACK_ALIGNMENT, old_alignment);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, old_framebuffer as GLuint);
+        }
+        pixels
+    }
+
+    /// Reads back the contents of the target into an RGBA image from the `image` crate, for
+    /// saving screenshots or comparing output.
+    ///
+    /// Like `read_pixels()`, this stalls until all drawing into the target has finished.
+    #[cfg(feature = "image")]
+    pub fn read_image(&self) -> RgbaImage {
+        RgbaImage::from_raw(self.width, self.height, self.read_pixels())
+            .expect("pixel buffer doesn't match the target size")
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteFramebuffers(1, &self.framebuffer);
+            self.gl.DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// Returns the pixel format and type that match the given sized internal format, falling back to
+/// 8-bit RGBA for formats that aren't listed.
+fn transfer_format(internal_format: GLenum) -> (GLenum, GLenum) {
+    match internal_format {
+        gl::R8 => (gl::RED, gl::UNSIGNED_BYTE),
+        gl::RG8 => (gl::RG, gl::UNSIGNED_BYTE),
+        gl::RGB8 | gl::SRGB8 => (gl::RGB, gl::UNSIGNED_BYTE),
+        gl::R16F => (gl::RED, gl::HALF_FLOAT),
+        gl::RG16F => (gl::RG, gl::HALF_FLOAT),
+        gl::RGB16F => (gl::RGB, gl::HALF_FLOAT),
+        gl::RGBA16F => (gl::RGBA, gl::HALF_FLOAT),
+        gl::R32F => (gl::RED, gl::FLOAT),
+        gl::RG32F => (gl::RG, gl::FLOAT),
+        gl::RGB32F | gl::R11F_G11F_B10F => (gl::RGB, gl::FLOAT),
+        gl::RGBA32F => (gl::RGBA, gl::FLOAT),
+        gl::RGB10_A2 => (gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV),
+        _ => (gl::RGBA, gl::UNSIGNED_BYTE),
+    }
+}
@@ -0,0 +1,403 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Textures uploaded from CPU pixel buffers.
+
+use {Gl, TextureTarget};
+
+use capabilities;
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+#[cfg(feature = "image")]
+use image::{DynamicImage, RgbaImage};
+use std::mem;
+use std::os::raw::c_void;
+
+/// A texture owned by the crate, uploaded from pixels in memory.
+///
+/// Rows of pixels are ordered from top to bottom, matching image files and the orientation the
+/// crate draws textures in. Draw it with `Context::draw_texture()`, or pass `id()` and `target()`
+/// to any of the other drawing methods.
+///
+/// The texture is deleted when this is dropped, so the GL context it was created in must be
+/// current at that time.
+pub struct Texture {
+    texture: GLuint,
+    target: TextureTarget,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    gl: Gl,
+}
+
+impl Texture {
+    /// Uploads 8-bit RGBA pixels into a new texture with the default options.
+    ///
+    /// You must have a current valid GL context before calling this. Panics if `pixels` isn't
+    /// exactly `width * height * 4` bytes long.
+    pub fn from_rgba8(gl: &Gl, width: u32, height: u32, pixels: &[u8]) -> Texture {
+        Texture::new(gl, width, height, TextureFormat::Rgba8, pixels)
+    }
+
+    /// Uploads 32-bit floating-point RGBA pixels into a new texture with the `Rgba16F` format
+    /// and the default options.
+    ///
+    /// You must have a current valid GL context before calling this. Panics if `pixels` isn't
+    /// exactly `width * height * 4` values long.
+    pub fn from_rgba_f32(gl: &Gl, width: u32, height: u32, pixels: &[f32]) -> Texture {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        unsafe {
+            Texture::upload(gl,
+                            width,
+                            height,
+                            TextureFormat::Rgba16F,
+                            gl::FLOAT,
+                            pixels.as_ptr() as *const c_void,
+                            &TextureOptions::default())
+        }
+    }
+
+    /// Uploads an image from the `image` crate into a new texture with the default options.
+    ///
+    /// RGB and RGBA images are uploaded as they are. Grayscale images are expanded to RGBA first,
+    /// so that they draw as gray rather than red.
+    ///
+    /// You must have a current valid GL context before calling this.
+    #[cfg(feature = "image")]
+    pub fn from_image(gl: &Gl, image: &DynamicImage) -> Texture {
+        match *image {
+            DynamicImage::ImageRgba8(ref image) => Texture::from_rgba_image(gl, image),
+            DynamicImage::ImageRgb8(ref image) => {
+                Texture::new(gl, image.width(), image.height(), TextureFormat::Rgb8, image)
+            }
+            _ => Texture::from_rgba_image(gl, &image.to_rgba()),
+        }
+    }
+
+    /// Uploads an RGBA image from the `image` crate into a new texture with the default options.
+    ///
+    /// You must have a current valid GL context before calling this.
+    #[cfg(feature = "image")]
+    pub fn from_rgba_image(gl: &Gl, image: &RgbaImage) -> Texture {
+        Texture::from_rgba8(gl, image.width(), image.height(), image)
+    }
+
+    /// Uploads pixels of the given format into a new texture with the default options.
+    ///
+    /// You must have a current valid GL context before calling this. Panics if `pixels` isn't
+    /// exactly `width * height * format.bytes_per_pixel()` bytes long.
+    pub fn new(gl: &Gl, width: u32, height: u32, format: TextureFormat, pixels: &[u8])
+               -> Texture {
+        Texture::with_options(gl, width, height, format, pixels, &TextureOptions::default())
+    }
+
+    /// Uploads pixels of the given format into a new texture, with all options specified
+    /// explicitly.
+    ///
+    /// You must have a current valid GL context before calling this. Panics if `pixels` isn't
+    /// exactly `width * height * format.bytes_per_pixel()` bytes long.
+    pub fn with_options(gl: &Gl,
+                        width: u32,
+                        height: u32,
+                        format: TextureFormat,
+                        pixels: &[u8],
+                        options: &TextureOptions)
+                        -> Texture {
+        assert_eq!(pixels.len(), width as usize * height as usize * format.bytes_per_pixel());
+        unsafe {
+            Texture::upload(gl,
+                            width,
+                            height,
+                            format,
+                            format.gl_type(),
+                            pixels.as_ptr() as *const c_void,
+                            options)
+        }
+    }
+
+    unsafe fn upload(gl: &Gl,
+                     width: u32,
+                     height: u32,
+                     format: TextureFormat,
+                     gl_type: GLenum,
+                     pixels: *const c_void,
+                     options: &TextureOptions)
+                     -> Texture {
+        let target = match options.target {
+            Some(target) => target,
+            None => capabilities::default_target(gl),
+        };
+        let mut texture = 0;
+        gl.GenTextures(1, &mut texture);
+        let texture = Texture {
+            texture: texture,
+            target: target,
+            format: format,
+            width: width,
+            height: height,
+            gl: gl.clone(),
+        };
+
+        // `GL_EXT_texture_format_BGRA8888` only accepts BGRA pixels into unsized BGRA textures.
+        let internal_format = if format == TextureFormat::Bgra8 && capabilities::is_es(gl) {
+            gl::BGRA
+        } else {
+            format.internal_format()
+        };
+
+        let _binding = Binding::new(&texture);
+        gl.TexImage2D(texture.target.gl_target(),
+                      0,
+                      internal_format as GLint,
+                      width as GLsizei,
+                      height as GLsizei,
+                      0,
+                      format.gl_format(),
+                      gl_type,
+                      pixels);
+        texture.apply_filter(options.filter);
+        texture.apply_wrap(options.wrap);
+        texture
+    }
+
+    /// Replaces the pixels in a region of the texture, given in pixels from the top left.
+    ///
+    /// `pixels` must be in the texture's format. For `Rgba16F` textures, they're half-precision
+    /// floats. Panics if the region doesn't fit within the texture, or if `pixels` isn't exactly
+    /// `width * height * bytes_per_pixel()` bytes long.
+    pub fn update(&self, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) {
+        assert!(x.checked_add(width).map_or(false, |right| right <= self.width) &&
+                y.checked_add(height).map_or(false, |bottom| bottom <= self.height),
+                "region doesn't fit within the texture");
+        assert_eq!(pixels.len(), width as usize * height as usize * self.format.bytes_per_pixel());
+        unsafe {
+            let _binding = Binding::new(self);
+            self.gl.TexSubImage2D(self.target.gl_target(),
+                                  0,
+                                  x as GLint,
+                                  y as GLint,
+                                  width as GLsizei,
+                                  height as GLsizei,
+                                  self.format.gl_format(),
+                                  self.format.gl_type(),
+                                  pixels.as_ptr() as *const c_void);
+        }
+    }
+
+    /// Changes how the texture is sampled between texels.
+    pub fn set_filter(&self, filter: TextureFilter) {
+        unsafe {
+            let _binding = Binding::new(self);
+            self.apply_filter(filter)
+        }
+    }
+
+    /// Changes how the texture is sampled outside its edges.
+    pub fn set_wrap(&self, wrap: TextureWrap) {
+        unsafe {
+            let _binding = Binding::new(self);
+            self.apply_wrap(wrap)
+        }
+    }
+
+    unsafe fn apply_filter(&self, filter: TextureFilter) {
+        let filter = match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        };
+        self.gl.TexParameteri(self.target.gl_target(), gl::TEXTURE_MIN_FILTER, filter as GLint);
+        self.gl.TexParameteri(self.target.gl_target(), gl::TEXTURE_MAG_FILTER, filter as GLint);
+    }
+
+    unsafe fn apply_wrap(&self, wrap: TextureWrap) {
+        let wrap = match wrap {
+            TextureWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl::REPEAT,
+            TextureWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        };
+        self.gl.TexParameteri(self.target.gl_target(), gl::TEXTURE_WRAP_S, wrap as GLint);
+        self.gl.TexParameteri(self.target.gl_target(), gl::TEXTURE_WRAP_T, wrap as GLint);
+    }
+
+    /// Returns the name of the underlying GL texture.
+    #[inline]
+    pub fn id(&self) -> GLuint {
+        self.texture
+    }
+
+    /// Returns the type of the texture.
+    #[inline]
+    pub fn target(&self) -> TextureTarget {
+        self.target
+    }
+
+    /// Returns the layout of the texture's pixels.
+    #[inline]
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Returns the width and height of the texture in pixels.
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// Binds a texture and sets tightly packed pixel unpacking, restoring the previous binding and
+/// unpack alignment when dropped.
+struct Binding {
+    target: GLenum,
+    old_texture: GLuint,
+    old_alignment: GLint,
+    gl: Gl,
+}
+
+impl Binding {
+    unsafe fn new(texture: &Texture) -> Binding {
+        let gl = &texture.gl;
+        let mut old_texture = 0;
+        gl.GetIntegerv(match texture.target {
+            TextureTarget::Rectangle => gl::TEXTURE_BINDING_RECTANGLE,
+            TextureTarget::Texture2D => gl::TEXTURE_BINDING_2D,
+        }, &mut old_texture);
+        let mut old_alignment = 0;
+        gl.GetIntegerv(gl::UNPACK_ALIGNMENT, &mut old_alignment);
+
+        gl.BindTexture(texture.target.gl_target(), texture.texture);
+        gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        Binding {
+            target: texture.target.gl_target(),
+            old_texture: old_texture as GLuint,
+            old_alignment: old_alignment,
+            gl: gl.clone(),
+        }
+    }
+}
+
+impl Drop for Binding {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, self.old_alignment);
+            self.gl.BindTexture(self.target, self.old_texture);
+        }
+    }
+}
+
+/// The layout of a texture's pixels, both in memory and on the GPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// 8-bit red, green, blue, and alpha.
+    Rgba8,
+    /// 8-bit blue, green, red, and alpha in memory, stored as `Rgba8` on the GPU. This is the
+    /// native layout of many platform image APIs. OpenGL ES only supports it with the
+    /// `GL_EXT_texture_format_BGRA8888` extension, which stores it as BGRA instead.
+    Bgra8,
+    /// 8-bit red, green, and blue, with alpha reading as 1.
+    Rgb8,
+    /// A single 8-bit channel, which reads as red.
+    R8,
+    /// Two 8-bit channels, which read as red and green.
+    Rg8,
+    /// Half-precision floating-point red, green, blue, and alpha, for high-dynamic-range images.
+    Rgba16F,
+}
+
+impl TextureFormat {
+    /// Returns the number of bytes each pixel occupies in memory.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 => 2,
+            TextureFormat::Rgb8 => 3,
+            TextureFormat::Rgba8 | TextureFormat::Bgra8 => 4,
+            TextureFormat::Rgba16F => 4 * mem::size_of::<u16>(),
+        }
+    }
+
+    fn internal_format(self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Bgra8 => gl::RGBA8,
+            TextureFormat::Rgb8 => gl::RGB8,
+            TextureFormat::R8 => gl::R8,
+            TextureFormat::Rg8 => gl::RG8,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+        }
+    }
+
+    fn gl_format(self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Rgba16F => gl::RGBA,
+            TextureFormat::Bgra8 => gl::BGRA,
+            TextureFormat::Rgb8 => gl::RGB,
+            TextureFormat::R8 => gl::RED,
+            TextureFormat::Rg8 => gl::RG,
+        }
+    }
+
+    fn gl_type(self) -> GLenum {
+        match self {
+            TextureFormat::Rgba16F => gl::HALF_FLOAT,
+            _ => gl::UNSIGNED_BYTE,
+        }
+    }
+}
+
+/// Options that control how a texture is created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// The type of texture to create. Defaults to `None`, which creates whatever type
+    /// `Context::draw()` expects in the current GL context: `TextureTarget::Rectangle` if it has
+    /// rectangle textures, and `TextureTarget::Texture2D` otherwise, as in OpenGL ES.
+    pub target: Option<TextureTarget>,
+    /// How the texture is sampled between texels. Defaults to `TextureFilter::Linear`.
+    pub filter: TextureFilter,
+    /// How the texture is sampled outside its edges. Defaults to `TextureWrap::ClampToEdge`.
+    /// Rectangle textures only support `ClampToEdge`.
+    pub wrap: TextureWrap,
+}
+
+impl Default for TextureOptions {
+    fn default() -> TextureOptions {
+        TextureOptions {
+            target: None,
+            filter: TextureFilter::Linear,
+            wrap: TextureWrap::ClampToEdge,
+        }
+    }
+}
+
+/// How a texture is sampled between texels, when drawn with `Filter::Native`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Uses the nearest texel.
+    Nearest,
+    /// Interpolates linearly between the four nearest texels.
+    Linear,
+}
+
+/// How a texture is sampled outside its edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureWrap {
+    /// Repeats the texels along the edges.
+    ClampToEdge,
+    /// Tiles the texture.
+    Repeat,
+    /// Tiles the texture, mirroring every other copy.
+    MirroredRepeat,
+}
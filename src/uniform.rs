@@ -0,0 +1,161 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Typed uniform values for custom shaders.
+
+use Gl;
+
+use gl;
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+use std::collections::HashMap;
+
+/// The value of a uniform in a custom fragment shader, as passed to `Context::set_uniform()`.
+///
+/// Every variant can be created with `From`, so you can pass `1.5f32`, `[0.0, 1.0]`, and so on
+/// directly. Matrices are column-major.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    /// An `int`, `bool`, or sampler.
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat3([[f32; 3]; 3]),
+    Mat4([[f32; 4]; 4]),
+}
+
+impl UniformValue {
+    /// Returns true if this value can be assigned to a uniform of the given GLSL type, as
+    /// reported by `glGetActiveUniform()`.
+    pub(crate) fn matches(&self, gl_type: GLenum) -> bool {
+        match *self {
+            UniformValue::Int(_) => {
+                match gl_type {
+                    gl::INT |
+                    gl::BOOL |
+                    gl::SAMPLER_2D |
+                    gl::SAMPLER_2D_RECT => true,
+                    _ => false,
+                }
+            }
+            UniformValue::Float(_) => gl_type == gl::FLOAT,
+            UniformValue::Vec2(_) => gl_type == gl::FLOAT_VEC2,
+            UniformValue::Vec3(_) => gl_type == gl::FLOAT_VEC3,
+            UniformValue::Vec4(_) => gl_type == gl::FLOAT_VEC4,
+            UniformValue::Mat3(_) => gl_type == gl::FLOAT_MAT3,
+            UniformValue::Mat4(_) => gl_type == gl::FLOAT_MAT4,
+        }
+    }
+
+    /// Uploads this value to the given location of the current program.
+    pub(crate) unsafe fn upload(&self, gl: &Gl, location: GLint) {
+        match *self {
+            UniformValue::Int(value) => gl.Uniform1i(location, value),
+            UniformValue::Float(value) => gl.Uniform1f(location, value),
+            UniformValue::Vec2(ref value) => gl.Uniform2fv(location, 1, value.as_ptr()),
+            UniformValue::Vec3(ref value) => gl.Uniform3fv(location, 1, value.as_ptr()),
+            UniformValue::Vec4(ref value) => gl.Uniform4fv(location, 1, value.as_ptr()),
+            UniformValue::Mat3(ref value) => {
+                gl.UniformMatrix3fv(location, 1, gl::FALSE, value[0].as_ptr())
+            }
+            UniformValue::Mat4(ref value) => {
+                gl.UniformMatrix4fv(location, 1, gl::FALSE, value[0].as_ptr())
+            }
+        }
+    }
+}
+
+impl From<i32> for UniformValue {
+    fn from(value: i32) -> UniformValue {
+        UniformValue::Int(value)
+    }
+}
+
+impl From<f32> for UniformValue {
+    fn from(value: f32) -> UniformValue {
+        UniformValue::Float(value)
+    }
+}
+
+impl From<[f32; 2]> for UniformValue {
+    fn from(value: [f32; 2]) -> UniformValue {
+        UniformValue::Vec2(value)
+    }
+}
+
+impl From<[f32; 3]> for UniformValue {
+    fn from(value: [f32; 3]) -> UniformValue {
+        UniformValue::Vec3(value)
+    }
+}
+
+impl From<[f32; 4]> for UniformValue {
+    fn from(value: [f32; 4]) -> UniformValue {
+        UniformValue::Vec4(value)
+    }
+}
+
+impl From<[[f32; 3]; 3]> for UniformValue {
+    fn from(value: [[f32; 3]; 3]) -> UniformValue {
+        UniformValue::Mat3(value)
+    }
+}
+
+impl From<[[f32; 4]; 4]> for UniformValue {
+    fn from(value: [[f32; 4]; 4]) -> UniformValue {
+        UniformValue::Mat4(value)
+    }
+}
+
+/// A uniform that's active in a linked program.
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveUniform {
+    pub location: GLint,
+    pub gl_type: GLenum,
+}
+
+/// Enumerates the active uniforms of a linked program with `glGetActiveUniform()`, keyed by
+/// name.
+///
+/// Arrays are listed under their base name, without the `[0]` suffix that some drivers add.
+pub unsafe fn active_uniforms(gl: &Gl, program: GLuint) -> HashMap<String, ActiveUniform> {
+    let mut count = 0;
+    gl.GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut max_length = 0;
+    gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut uniforms = HashMap::new();
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    for index in 0..(count.max(0) as GLuint) {
+        let (mut length, mut size, mut gl_type) = (0, 0, 0);
+        gl.GetActiveUniform(program,
+                            index,
+                            name.len() as GLsizei,
+                            &mut length,
+                            &mut size,
+                            &mut gl_type,
+                            name.as_mut_ptr() as *mut GLchar);
+        let mut name = String::from_utf8_lossy(&name[..(length.max(0) as usize)]).into_owned();
+        if name.ends_with("[0]") {
+            let base_length = name.len() - 3;
+            name.truncate(base_length);
+        }
+
+        let mut c_name = name.clone().into_bytes();
+        c_name.push(0);
+        let location = gl.GetUniformLocation(program, c_name.as_ptr() as *const GLchar);
+        uniforms.insert(name, ActiveUniform {
+            location: location,
+            gl_type: gl_type,
+        });
+    }
+    uniforms
+}
@@ -0,0 +1,105 @@
+// Copyright 2017 Mozilla Foundation. See the COPYRIGHT file
+// at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Planar YUV video frames.
+
+use gl::types::GLuint;
+
+/// The plane textures of a planar YUV video frame, for `Context::draw_yuv()`.
+///
+/// All planes must be of the texture type given by `DrawOptions::target`. Chroma planes may be
+/// subsampled; they're sampled with normalized coordinates, so only their aspect ratio matters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvPlanes {
+    /// A luma plane followed by a single plane of interleaved Cb and Cr samples. The luma plane
+    /// should be single-channel (for example, `GL_R8`) and the chroma plane two-channel (`GL_RG8`).
+    Nv12 {
+        y: GLuint,
+        uv: GLuint,
+    },
+    /// Separate luma, Cb, and Cr planes, each single-channel (for example, `GL_R8`).
+    I420 {
+        y: GLuint,
+        u: GLuint,
+        v: GLuint,
+    },
+}
+
+/// How to convert YUV samples to RGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct YuvOptions {
+    /// The matrix coefficients. Defaults to `YuvColorSpace::Bt709`.
+    pub color_space: YuvColorSpace,
+    /// The range of the samples. Defaults to `YuvRange::Limited`.
+    pub range: YuvRange,
+}
+
+impl Default for YuvOptions {
+    fn default() -> YuvOptions {
+        YuvOptions {
+            color_space: YuvColorSpace::Bt709,
+            range: YuvRange::Limited,
+        }
+    }
+}
+
+/// The standard defining the YUV to RGB matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvColorSpace {
+    /// ITU-R BT.601, used for standard-definition video.
+    Bt601,
+    /// ITU-R BT.709, used for high-definition video.
+    Bt709,
+    /// ITU-R BT.2020, used for ultra-high-definition video.
+    Bt2020,
+}
+
+/// The range of values that YUV samples occupy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvRange {
+    /// "Studio swing": luma from 16 to 235 and chroma from 16 to 240, in 8-bit terms. Most video
+    /// is encoded this way.
+    Limited,
+    /// "Full swing": all components from 0 to 255, in 8-bit terms. Used by JPEG.
+    Full,
+}
+
+impl YuvOptions {
+    /// Returns the column-major matrix and the offset that convert normalized YUV samples to RGB,
+    /// as `matrix * (yuv - offset)`.
+    pub(crate) fn conversion(&self) -> ([[f32; 3]; 3], [f32; 3]) {
+        let (kr, kb) = match self.color_space {
+            YuvColorSpace::Bt601 => (0.299, 0.114),
+            YuvColorSpace::Bt709 => (0.2126, 0.0722),
+            YuvColorSpace::Bt2020 => (0.2627, 0.0593),
+        };
+        let kg = 1.0 - kr - kb;
+
+        let (luma_scale, chroma_scale, luma_offset) = match self.range {
+            YuvRange::Limited => (255.0 / 219.0, 255.0 / 224.0, 16.0 / 255.0),
+            YuvRange::Full => (1.0, 1.0, 0.0),
+        };
+
+        let matrix = [
+            [luma_scale, luma_scale, luma_scale],
+            [
+                0.0,
+                -2.0 * kb * (1.0 - kb) / kg * chroma_scale,
+                2.0 * (1.0 - kb) * chroma_scale,
+            ],
+            [
+                2.0 * (1.0 - kr) * chroma_scale,
+                -2.0 * kr * (1.0 - kr) / kg * chroma_scale,
+                0.0,
+            ],
+        ];
+        (matrix, [luma_offset, 128.0 / 255.0, 128.0 / 255.0])
+    }
+}
@@ -0,0 +1,63 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for detecting what the GL context supports.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::Headless;
+use lord_drawquaad::{Api, Capabilities, Context, ContextOptions, Error, Profile};
+
+#[test]
+fn core_profile_is_detected() {
+    let headless = Headless::new();
+    let capabilities = Capabilities::current(&headless.gl);
+    assert_eq!(capabilities.api, Api::Gl);
+    assert_eq!(capabilities.profile, Profile::Core);
+    assert!(capabilities.version >= (3, 3));
+    assert!(capabilities.glsl_version >= (3, 30));
+    assert!(capabilities.max_texture_size >= 1024);
+    assert!(capabilities.max_texture_units >= 48);
+    assert!(capabilities.rectangle_textures);
+    assert!(capabilities.float_textures);
+    assert!(!capabilities.has_extension("GL_LORD_drawquaad"));
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn api_can_be_overridden() {
+    let headless = Headless::compatibility();
+    let options = ContextOptions { api: Some(Api::LegacyGl), ..ContextOptions::default() };
+    let context = Context::with_options(&headless.gl, &options).unwrap();
+    assert_eq!(context.api(), Api::LegacyGl);
+    assert_eq!(context.capabilities().profile, Profile::Compatibility);
+}
+
+#[test]
+fn legacy_api_is_rejected_in_core_profiles() {
+    let headless = Headless::new();
+    let options = ContextOptions { api: Some(Api::LegacyGl), ..ContextOptions::default() };
+    match Context::with_options(&headless.gl, &options) {
+        Err(Error::UnsupportedApi { api: Api::LegacyGl }) => {}
+        Err(error) => panic!("unexpected error: {}", error),
+        Ok(_) => panic!("creating a legacy context in a core profile succeeded"),
+    }
+}
+
+#[test]
+fn api_is_chosen_from_version_strings() {
+    assert_eq!(Api::from_version_strings("4.5 (Core Profile) Mesa 23.2.1", "4.50"), Api::Gl);
+    assert_eq!(Api::from_version_strings("2.1 Mesa 23.2.1", "1.20"), Api::LegacyGl);
+    assert_eq!(Api::from_version_strings("OpenGL ES 3.2 Mesa 23.2.1", "OpenGL ES GLSL ES 3.20"),
+               Api::Gles);
+    assert_eq!(Api::from_version_strings("WebGL 2.0 (OpenGL ES 3.0 Chromium)",
+                                         "WebGL GLSL ES 3.00 (OpenGL ES GLSL ES 3.0 Chromium)"),
+               Api::Gles);
+}
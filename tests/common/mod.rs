@@ -0,0 +1,286 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Shared setup for the headless test suite.
+//!
+//! Tests run against whatever EGL implementation is installed, which is expected to be Mesa's
+//! llvmpipe software rasterizer. Set `LIBGL_ALWAYS_SOFTWARE=1` to force it on machines with a GPU.
+//! Golden images live in `tests/golden`; run with `LORD_DRAWQUAAD_BLESS=1` to regenerate them.
+
+#![allow(dead_code)]
+
+use egl;
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use image;
+use lord_drawquaad::{Gl, RenderTarget, TextureTarget};
+use std::env;
+use std::fs;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Mutex;
+
+/// The most any channel of a rendered pixel may differ from its golden image.
+const GOLDEN_TOLERANCE: u8 = 2;
+
+const EGL_PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31dd;
+
+/// Held while creating a context, since that writes process-wide state that tests on other
+/// threads read: the global function pointers of the `gl` crate, and for legacy contexts the
+/// environment.
+static CREATION_LOCK: Mutex<()> = Mutex::new(());
+
+/// A GL context with no window, current on the calling thread until dropped.
+///
+/// The tests make their own raw GL calls through the global functions of the `gl` crate, while
+/// the crate under test calls through `gl`, a separately loaded table.
+pub struct Headless {
+    pub gl: Gl,
+    egl: egl::Instance<egl::Static>,
+    display: egl::Display,
+    surface: Option<egl::Surface>,
+    context: egl::Context,
+}
+
+impl Headless {
+    /// Creates a desktop GL 3.3 core profile context.
+    pub fn new() -> Headless {
+        Headless::with_api(egl::OPENGL_API, egl::OPENGL_BIT, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE,
+        ])
+    }
+
+    /// Creates a desktop GL 3.3 compatibility profile context.
+    pub fn compatibility() -> Headless {
+        Headless::with_api(egl::OPENGL_API, egl::OPENGL_BIT, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT,
+            egl::NONE,
+        ])
+    }
+
+    /// Creates a desktop GL 2.1 context. With Mesa, this reports version 2.1 so that detection
+    /// picks the legacy path. Mesa only reads the override once, so every other context created
+    /// in the same process reports 2.1 too.
+    pub fn legacy() -> Headless {
+        Headless::with_api(egl::OPENGL_API, egl::OPENGL_BIT, Some("2.1"), &[
+            egl::CONTEXT_MAJOR_VERSION, 2,
+            egl::CONTEXT_MINOR_VERSION, 1,
+            egl::NONE,
+        ])
+    }
+
+    /// Creates an OpenGL ES 3.0 context.
+    pub fn gles() -> Headless {
+        Headless::with_api(egl::OPENGL_ES_API, egl::OPENGL_ES3_BIT, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 3,
+            egl::CONTEXT_MINOR_VERSION, 0,
+            egl::NONE,
+        ])
+    }
+
+    /// Looks up a GL function for loaders other than the `gl` crate and `Gl`, returning null if
+    /// it doesn't exist.
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.egl.get_proc_address(symbol).map_or(ptr::null(), |function| function as *const c_void)
+    }
+
+    /// Makes this context current on the calling thread again, after another was created.
+    pub fn make_current(&self) {
+        self.egl.make_current(self.display, self.surface, self.surface, Some(self.context))
+                .unwrap();
+    }
+
+    fn with_api(api: egl::Enum,
+                renderable_type: egl::Int,
+                version_override: Option<&str>,
+                context_attributes: &[egl::Int])
+                -> Headless {
+        let _lock = CREATION_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+        if let Some(version) = version_override {
+            env::set_var("MESA_GL_VERSION_OVERRIDE", version);
+        }
+
+        let egl = egl::Instance::new(egl::Static);
+
+        // Prefer a surfaceless display, which doesn't need a window system at all, and fall back
+        // to a pbuffer on the default display.
+        let surfaceless = unsafe {
+            egl.get_platform_display(EGL_PLATFORM_SURFACELESS_MESA,
+                                     egl::DEFAULT_DISPLAY,
+                                     &[egl::ATTRIB_NONE])
+        };
+        let (display, surfaceless) = match surfaceless {
+            Ok(display) => (display, true),
+            Err(_) => {
+                let display = unsafe { egl.get_display(egl::DEFAULT_DISPLAY) };
+                (display.expect("No EGL display is available!"), false)
+            }
+        };
+        egl.initialize(display).expect("Couldn't initialize EGL!");
+        egl.bind_api(api).expect("EGL doesn't support the requested API!");
+
+        let config = egl.choose_first_config(display, &[
+            egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+            egl::RENDERABLE_TYPE, renderable_type,
+            egl::NONE,
+        ]).unwrap().expect("No suitable EGL config!");
+        let context = egl.create_context(display, config, None, context_attributes)
+                         .expect("Couldn't create a GL context!");
+
+        let surface = if surfaceless {
+            None
+        } else {
+            Some(egl.create_pbuffer_surface(display, config, &[
+                egl::WIDTH, 1,
+                egl::HEIGHT, 1,
+                egl::NONE,
+            ]).expect("Couldn't create a pbuffer!"))
+        };
+        egl.make_current(display, surface, surface, Some(context)).unwrap();
+
+        let get_proc_address = |symbol: &str| {
+            egl.get_proc_address(symbol).map_or(ptr::null(), |function| function as *const c_void)
+        };
+        gl::load_with(&get_proc_address);
+        let functions = Gl::load_with(&get_proc_address);
+
+        Headless {
+            gl: functions,
+            egl: egl,
+            display: display,
+            surface: surface,
+            context: context,
+        }
+    }
+}
+
+impl Drop for Headless {
+    fn drop(&mut self) {
+        self.egl.make_current(self.display, None, None, None).unwrap();
+        self.egl.destroy_context(self.display, self.context).unwrap();
+        if let Some(surface) = self.surface {
+            self.egl.destroy_surface(self.display, surface).unwrap();
+        }
+    }
+}
+
+pub const RED: [u8; 4] = [255, 0, 0, 255];
+pub const GREEN: [u8; 4] = [0, 255, 0, 255];
+pub const BLUE: [u8; 4] = [0, 0, 255, 255];
+pub const WHITE: [u8; 4] = [255, 255, 255, 255];
+pub const BLACK: [u8; 4] = [0, 0, 0, 255];
+pub const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+
+/// A 2x2 image with a different color in each corner: red, green, blue, and white in reading
+/// order.
+pub fn corners() -> Vec<u8> {
+    [RED, GREEN, BLUE, WHITE].iter().flat_map(|pixel| pixel.iter().cloned()).collect()
+}
+
+/// An 8x8 image whose red channel increases to the right and green channel increases downward,
+/// with a checkerboard in blue, so that any misplaced texel shows up.
+pub fn gradient() -> Vec<u8> {
+    let mut pixels = vec![];
+    for y in 0..8 {
+        for x in 0..8 {
+            pixels.extend_from_slice(&[x * 32 + 16, y * 32 + 16, 128 + (x + y) % 2 * 100, 255]);
+        }
+    }
+    pixels
+}
+
+/// Uploads 8-bit RGBA pixels into a new texture with nearest filtering.
+pub fn upload(target: TextureTarget, width: u32, height: u32, pixels: &[u8]) -> GLuint {
+    upload_with_format(target, width, height, gl::RGBA8, gl::RGBA, pixels)
+}
+
+/// Uploads 8-bit pixels of any layout into a new texture with nearest filtering.
+pub fn upload_with_format(target: TextureTarget,
+                          width: u32,
+                          height: u32,
+                          internal_format: GLenum,
+                          format: GLenum,
+                          pixels: &[u8])
+                          -> GLuint {
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(target.gl_target(), texture);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(target.gl_target(),
+                       0,
+                       internal_format as GLint,
+                       width as GLsizei,
+                       height as GLsizei,
+                       0,
+                       format,
+                       gl::UNSIGNED_BYTE,
+                       pixels.as_ptr() as *const c_void);
+        gl::TexParameteri(target.gl_target(), gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(target.gl_target(), gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        gl::BindTexture(target.gl_target(), 0);
+    }
+    texture
+}
+
+/// Creates an 8-bit RGBA render target cleared to opaque black.
+pub fn render_target(gl: &Gl, width: u32, height: u32) -> RenderTarget {
+    let target = RenderTarget::new(gl, width, height, TextureTarget::Texture2D).unwrap();
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer());
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+    target
+}
+
+/// Returns the pixel at the given position in a top-down RGBA buffer.
+pub fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+    let index = (y * width + x) as usize * 4;
+    [pixels[index], pixels[index + 1], pixels[index + 2], pixels[index + 3]]
+}
+
+/// Asserts that no GL errors have been raised.
+pub fn assert_no_gl_error() {
+    assert_eq!(unsafe { gl::GetError() }, gl::NO_ERROR);
+}
+
+/// Compares the contents of a render target against `tests/golden/<name>.png`.
+///
+/// On a mismatch, the actual image is written to `target/golden/<name>.png` for inspection.
+pub fn assert_golden(name: &str, target: &RenderTarget) {
+    let (width, height) = target.size();
+    let actual = target.read_pixels();
+
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let golden_path = manifest_dir.join("tests").join("golden").join(format!("{}.png", name));
+    if env::var_os("LORD_DRAWQUAAD_BLESS").is_some() {
+        image::save_buffer(&golden_path, &actual, width, height, image::RGBA(8)).unwrap();
+        return
+    }
+
+    let golden = match image::open(&golden_path) {
+        Ok(golden) => golden.to_rgba(),
+        Err(error) => panic!("Couldn't open golden image {}: {}", golden_path.display(), error),
+    };
+    let matches = golden.width() == width && golden.height() == height &&
+        golden.iter().zip(actual.iter()).all(|(&expected, &actual)| {
+            (expected as i32 - actual as i32).abs() <= GOLDEN_TOLERANCE as i32
+        });
+    if !matches {
+        let failure_dir = manifest_dir.join("target").join("golden");
+        fs::create_dir_all(&failure_dir).unwrap();
+        let failure_path = failure_dir.join(format!("{}.png", name));
+        image::save_buffer(&failure_path, &actual, width, height, image::RGBA(8)).unwrap();
+        panic!("Rendering doesn't match golden image {}; the actual output is in {}",
+               golden_path.display(),
+               failure_path.display());
+    }
+}
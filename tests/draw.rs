@@ -0,0 +1,296 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for drawing textures with the built-in shaders.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLACK, BLUE, GREEN, Headless, RED, WHITE};
+use gl::types::GLint;
+use lord_drawquaad::{AlphaConversion, Batch, BlendMode, Context, ContextOptions, DrawOptions};
+use lord_drawquaad::{Filter, FitMode, Orientation, Rect, Rotation, TextureTarget};
+
+#[test]
+fn draw_fills_the_viewport() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let target = common::render_target(&headless.gl, 4, 4);
+    for &texture_target in &[TextureTarget::Rectangle, TextureTarget::Texture2D] {
+        let texture = common::upload(texture_target, 2, 2, &common::corners());
+        context.render_to(&target, || context.draw_with_target(texture, texture_target));
+
+        let pixels = target.read_pixels();
+        assert_eq!(common::pixel(&pixels, 4, 0, 0), RED);
+        assert_eq!(common::pixel(&pixels, 4, 3, 0), GREEN);
+        assert_eq!(common::pixel(&pixels, 4, 0, 3), BLUE);
+        assert_eq!(common::pixel(&pixels, 4, 3, 3), WHITE);
+    }
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn draw_matches_golden_image() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 8, 8, &common::gradient());
+    let target = common::render_target(&headless.gl, 32, 24);
+    context.render_to(&target, || context.draw(texture));
+    common::assert_golden("gradient", &target);
+}
+
+#[test]
+fn draw_region_maps_src_to_dest() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+    context.render_to(&target, || {
+        context.draw_region(texture,
+                            Rect::pixels(1.0, 1.0, 1.0, 1.0),
+                            Rect::pixels(0.0, 0.0, 2.0, 2.0))
+    });
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 1, 1), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 2, 2), BLACK);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), BLACK);
+}
+
+#[test]
+fn orientation_flips_and_rotates() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+
+    let cases = [
+        (Orientation::default(), RED, GREEN),
+        (Orientation { flip_x: true, ..Orientation::default() }, GREEN, RED),
+        (Orientation { flip_y: true, ..Orientation::default() }, BLUE, WHITE),
+        (Orientation { rotation: Rotation::Rotate90, ..Orientation::default() }, BLUE, RED),
+        (Orientation { rotation: Rotation::Rotate180, ..Orientation::default() }, WHITE, BLUE),
+        (Orientation { rotation: Rotation::Rotate270, ..Orientation::default() }, GREEN, WHITE),
+    ];
+    for &(orientation, top_left, top_right) in &cases {
+        let options = DrawOptions { orientation: orientation, ..DrawOptions::default() };
+        context.draw_to(&target, texture, &options);
+
+        let pixels = target.read_pixels();
+        assert_eq!((common::pixel(&pixels, 4, 0, 0), common::pixel(&pixels, 4, 3, 0)),
+                   (top_left, top_right),
+                   "{:?}",
+                   orientation);
+    }
+}
+
+#[test]
+fn transform_applies_in_clip_space() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+    let mirror = [
+        [-1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    context.render_to(&target, || context.draw_transformed(texture, mirror));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), GREEN);
+    assert_eq!(common::pixel(&pixels, 4, 3, 3), BLUE);
+}
+
+#[test]
+fn fit_modes_match_golden_images() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 8, 8, &common::gradient());
+    let target = common::render_target(&headless.gl, 32, 16);
+    let cases = [
+        (FitMode::Contain, "fit_contain"),
+        (FitMode::Cover, "fit_cover"),
+        (FitMode::Center, "fit_center"),
+    ];
+    for &(fit, name) in &cases {
+        let options = DrawOptions {
+            fit: fit,
+            background: Some([0.0, 0.0, 0.0, 1.0]),
+            ..DrawOptions::default()
+        };
+        context.draw_to(&target, texture, &options);
+        common::assert_golden(name, &target);
+    }
+}
+
+#[test]
+fn background_only_covers_dest() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &RED);
+    let target = common::render_target(&headless.gl, 4, 4);
+    let options = DrawOptions {
+        dest: Rect::pixels(0.0, 0.0, 3.0, 3.0),
+        fit: FitMode::Center,
+        background: Some([0.0, 0.0, 1.0, 1.0]),
+        ..DrawOptions::default()
+    };
+    context.draw_to(&target, texture, &options);
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 1, 1), RED);
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), BLUE);
+    assert_eq!(common::pixel(&pixels, 4, 2, 2), BLUE);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), BLACK);
+    assert_eq!(common::pixel(&pixels, 4, 0, 3), BLACK);
+}
+
+#[test]
+fn filters_match_golden_images() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 8, 8, &common::gradient());
+    let cases = [
+        (Filter::CatmullRom, "filter_catmull_rom", 32),
+        (Filter::Lanczos3, "filter_lanczos3", 32),
+        (Filter::Area, "filter_area", 3),
+    ];
+    for &(filter, name, size) in &cases {
+        let target = common::render_target(&headless.gl, size, size);
+        let options = DrawOptions { filter: filter, ..DrawOptions::default() };
+        context.draw_to(&target, texture, &options);
+        common::assert_golden(name, &target);
+    }
+}
+
+#[test]
+fn area_filter_averages_texels() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let mut checkerboard = vec![];
+    for y in 0..8 {
+        for x in 0..8 {
+            checkerboard.extend_from_slice(if (x + y) % 2 == 0 { &WHITE } else { &BLACK });
+        }
+    }
+    let texture = common::upload(TextureTarget::Rectangle, 8, 8, &checkerboard);
+    let target = common::render_target(&headless.gl, 1, 1);
+    let options = DrawOptions { filter: Filter::Area, ..DrawOptions::default() };
+    context.draw_to(&target, texture, &options);
+
+    let pixel = common::pixel(&target.read_pixels(), 1, 0, 0);
+    assert!(pixel[0] >= 126 && pixel[0] <= 129, "{:?}", pixel);
+}
+
+#[test]
+fn blend_modes_and_alpha_conversion() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &[0, 0, 255, 128]);
+    let target = common::render_target(&headless.gl, 1, 1);
+    let draw = |blend, alpha| {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer());
+            gl::ClearColor(1.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        let options = DrawOptions { blend: Some(blend), alpha: alpha, ..DrawOptions::default() };
+        context.draw_to(&target, texture, &options);
+        common::pixel(&target.read_pixels(), 1, 0, 0)
+    };
+
+    assert_eq!(draw(BlendMode::Replace, AlphaConversion::Keep), [0, 0, 255, 128]);
+    assert_eq!(draw(BlendMode::Replace, AlphaConversion::Premultiply), [0, 0, 128, 128]);
+    let over = draw(BlendMode::AlphaOverStraight, AlphaConversion::Keep);
+    assert!(over[0] >= 126 && over[0] <= 128 && over[2] >= 127 && over[2] <= 129, "{:?}", over);
+    assert_eq!(draw(BlendMode::Additive, AlphaConversion::Keep), [255, 0, 255, 255]);
+
+    // Blending is switched back off afterward.
+    assert_eq!(unsafe { gl::IsEnabled(gl::BLEND) }, gl::FALSE);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn batch_matches_individual_draws() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let corners = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let gradient = common::upload(TextureTarget::Rectangle, 8, 8, &common::gradient());
+    let quads = [
+        (gradient, Rect::normalized(0.0, 0.0, 1.0, 1.0), Rect::normalized(0.0, 0.0, 0.5, 0.5)),
+        (corners, Rect::pixels(1.0, 0.0, 1.0, 2.0), Rect::pixels(16.0, 0.0, 8.0, 16.0)),
+        (gradient, Rect::pixels(2.0, 2.0, 4.0, 4.0), Rect::normalized(0.25, 0.5, 0.75, 0.5)),
+        (corners, Rect::normalized(0.0, 0.0, 1.0, 1.0), Rect::pixels(24.0, 4.0, 8.0, 8.0)),
+    ];
+
+    let batched = common::render_target(&headless.gl, 32, 32);
+    let mut batch = Batch::new();
+    for &(texture, src, dest) in &quads {
+        batch.add(texture, src, dest);
+    }
+    context.render_to(&batched, || context.draw_batch(&batch));
+
+    let individual = common::render_target(&headless.gl, 32, 32);
+    context.render_to(&individual, || {
+        for &(texture, src, dest) in &quads {
+            context.draw_region(texture, src, dest)
+        }
+    });
+
+    assert!(batched.read_pixels() == individual.read_pixels());
+    common::assert_golden("batch", &batched);
+}
+
+#[test]
+fn preserve_state_restores_bindings() {
+    let headless = Headless::new();
+    let options = ContextOptions { preserve_state: true, ..ContextOptions::default() };
+    let context = Context::with_options(&headless.gl, &options).unwrap();
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let other_texture = common::upload(TextureTarget::Rectangle, 1, 1, &RED);
+    let target = common::render_target(&headless.gl, 4, 4);
+
+    let get = |parameter| {
+        let mut value = 0;
+        unsafe {
+            gl::GetIntegerv(parameter, &mut value);
+        }
+        value
+    };
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE3);
+        gl::BindTexture(gl::TEXTURE_RECTANGLE, other_texture);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_RECTANGLE, other_texture);
+        gl::ActiveTexture(gl::TEXTURE3);
+    }
+    context.render_to(&target, || {
+        context.draw(texture);
+        let mut batch = Batch::new();
+        let whole = Rect::normalized(0.0, 0.0, 1.0, 1.0);
+        batch.add(texture, whole, whole);
+        context.draw_batch(&batch);
+    });
+
+    assert_eq!(get(gl::CURRENT_PROGRAM), 0);
+    assert_eq!(get(gl::VERTEX_ARRAY_BINDING), 0);
+    assert_eq!(get(gl::ARRAY_BUFFER_BINDING), 0);
+    assert_eq!(get(gl::FRAMEBUFFER_BINDING), 0);
+    assert_eq!(get(gl::ACTIVE_TEXTURE) as u32, gl::TEXTURE3);
+    assert_eq!(get(gl::TEXTURE_BINDING_RECTANGLE), other_texture as GLint);
+    unsafe {
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+    assert_eq!(get(gl::TEXTURE_BINDING_RECTANGLE), other_texture as GLint);
+    common::assert_no_gl_error();
+}
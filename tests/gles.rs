@@ -0,0 +1,180 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for drawing under OpenGL ES 3.0.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLACK, BLUE, Headless, RED, WHITE};
+use lord_drawquaad::{Api, Batch, Context, ContextOptions, DrawOptions, Filter, Profile, Rect};
+use lord_drawquaad::{RenderTarget, Texture, TextureFormat, TextureTarget};
+use lord_drawquaad::{YuvOptions, YuvPlanes};
+
+#[test]
+fn api_is_detected() {
+    let headless = Headless::gles();
+    assert_eq!(Api::current(&headless.gl), Api::Gles);
+    let context = Context::new(&headless.gl);
+    assert_eq!(context.api(), Api::Gles);
+
+    let capabilities = context.capabilities();
+    assert_eq!(capabilities.profile, Profile::Es);
+    assert!(capabilities.version >= (3, 0));
+    assert!(capabilities.glsl_version >= (3, 0));
+    assert!(!capabilities.rectangle_textures);
+    assert!(capabilities.float_textures);
+}
+
+#[test]
+fn draw_matches_desktop_golden_images() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    let texture = Texture::from_rgba8(&headless.gl, 8, 8, &common::gradient());
+    texture.set_filter(lord_drawquaad::TextureFilter::Nearest);
+
+    let target = common::render_target(&headless.gl, 32, 24);
+    context.render_to(&target, || context.draw_texture(&texture));
+    common::assert_golden("gradient", &target);
+
+    let target = common::render_target(&headless.gl, 32, 32);
+    let options = DrawOptions { filter: Filter::CatmullRom, ..DrawOptions::default() };
+    context.render_to(&target, || context.draw_texture_with_options(&texture, &options));
+    common::assert_golden("filter_catmull_rom", &target);
+}
+
+#[test]
+fn render_targets_and_batches_work() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    let texture = Texture::from_rgba8(&headless.gl, 2, 2, &common::corners());
+    texture.set_filter(lord_drawquaad::TextureFilter::Nearest);
+
+    let intermediate = RenderTarget::new(&headless.gl, 2, 2, TextureTarget::Texture2D).unwrap();
+    context.render_to(&intermediate, || context.draw_texture(&texture));
+    assert_eq!(intermediate.read_pixels(), common::corners());
+
+    let target = common::render_target(&headless.gl, 4, 4);
+    let mut batch = Batch::with_target(TextureTarget::Texture2D);
+    batch.add(intermediate.texture(),
+              Rect::pixels(1.0, 1.0, 1.0, 1.0),
+              Rect::pixels(0.0, 0.0, 1.0, 1.0));
+    context.render_to(&target, || context.draw_batch(&batch));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 1, 1), BLACK);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn float_render_targets_work() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    let result = RenderTarget::with_format(&headless.gl,
+                                           2,
+                                           2,
+                                           TextureTarget::Texture2D,
+                                           gl::RGBA16F);
+    common::assert_no_gl_error();
+    if !context.capabilities().has_extension("GL_EXT_color_buffer_float") {
+        return
+    }
+
+    // Float framebuffers can't be read back as 8-bit RGBA in ES, so copy through another target.
+    let intermediate = result.unwrap();
+    let texture = Texture::from_rgba8(&headless.gl, 2, 2, &common::corners());
+    texture.set_filter(lord_drawquaad::TextureFilter::Nearest);
+    context.render_to(&intermediate, || context.draw_texture(&texture));
+    let target = common::render_target(&headless.gl, 2, 2);
+    context.render_to(&target, || context.draw(intermediate.texture()));
+    assert_eq!(target.read_pixels(), common::corners());
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn bgra_textures_work_with_the_extension() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    if !context.capabilities().has_extension("GL_EXT_texture_format_BGRA8888") {
+        return
+    }
+
+    let texture = Texture::new(&headless.gl, 1, 1, TextureFormat::Bgra8, &[255, 0, 0, 255]);
+    texture.update(0, 0, 1, 1, &[255, 0, 0, 255]);
+    let target = common::render_target(&headless.gl, 1, 1);
+    context.render_to(&target, || context.draw_texture(&texture));
+    assert_eq!(common::pixel(&target.read_pixels(), 1, 0, 0), BLUE);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn custom_shaders_and_yuv_compile() {
+    let headless = Headless::gles();
+    let mut context = Context::with_options(&headless.gl, &ContextOptions {
+        fragment_shader: Some(r#"
+uniform vec4 uTint;
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = sampleTexture(vTexCoord).bgra * uTint;
+}
+"#.to_owned()),
+        preserve_state: true,
+        ..ContextOptions::default()
+    }).unwrap();
+    context.set_uniform("uTint", [1.0f32, 1.0, 1.0, 1.0]).unwrap();
+    let texture = Texture::from_rgba8(&headless.gl, 1, 1, &RED);
+    let target = common::render_target(&headless.gl, 1, 1);
+    context.render_to(&target, || context.draw_texture(&texture));
+    assert_eq!(common::pixel(&target.read_pixels(), 1, 0, 0), BLUE);
+
+    // BT.709 limited-range red.
+    let y = Texture::new(&headless.gl, 1, 1, TextureFormat::R8, &[63]);
+    let uv = Texture::new(&headless.gl, 1, 1, TextureFormat::Rg8, &[102, 240]);
+    context.render_to(&target, || {
+        context.draw_yuv(YuvPlanes::Nv12 { y: y.id(), uv: uv.id() }, &YuvOptions::default())
+    });
+    let pixel = common::pixel(&target.read_pixels(), 1, 0, 0);
+    assert!(pixel[0] > 250 && pixel[1] < 5 && pixel[2] < 5, "{:?}", pixel);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn simple_api_uses_2d_textures() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    assert_eq!(context.default_target(), TextureTarget::Texture2D);
+    let texture = Texture::from_rgba8(&headless.gl, 2, 2, &common::corners());
+    assert_eq!(texture.target(), TextureTarget::Texture2D);
+    texture.set_filter(lord_drawquaad::TextureFilter::Nearest);
+
+    let target = common::render_target(&headless.gl, 4, 4);
+    context.render_to(&target, || {
+        context.draw(texture.id());
+        context.draw_rect(texture.id(), Rect::pixels(0.0, 0.0, 2.0, 2.0));
+        let mut batch = Batch::new();
+        batch.add(texture.id(), Rect::pixels(1.0, 1.0, 1.0, 1.0), Rect::pixels(2.0, 0.0, 2.0, 2.0));
+        context.draw_batch(&batch);
+    });
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), RED);
+    assert_eq!(common::pixel(&pixels, 4, 1, 1), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 2, 0), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 0, 3), BLUE);
+    common::assert_no_gl_error();
+}
+
+#[test]
+#[should_panic(expected = "rectangle textures")]
+fn rectangle_textures_are_rejected() {
+    let headless = Headless::gles();
+    let context = Context::new(&headless.gl);
+    context.draw_with_target(0, TextureTarget::Rectangle);
+}
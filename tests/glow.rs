@@ -0,0 +1,104 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for drawing through `glow` with `GlowContext`.
+
+#![cfg(all(feature = "glow", target_os = "linux"))]
+
+extern crate gl;
+extern crate glow;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLACK, BLUE, GREEN, Headless, RED, WHITE};
+use gl::types::GLsizei;
+use lord_drawquaad::{Api, BlendMode, DrawOptions, GlowContext, Orientation, Rect};
+use lord_drawquaad::{RenderTarget, TextureTarget};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+fn glow_context(headless: &Headless) -> Arc<glow::Context> {
+    unsafe {
+        Arc::new(glow::Context::from_loader_function(|symbol| headless.get_proc_address(symbol)))
+    }
+}
+
+fn glow_texture(texture: u32) -> glow::Texture {
+    glow::NativeTexture(NonZeroU32::new(texture).unwrap())
+}
+
+/// Binds a render target for drawing with raw GL calls. Unlike `Context::render_to()`, this
+/// doesn't flip anything, so the target's contents end up bottom-up.
+fn bind(target: &RenderTarget) {
+    let (width, height) = target.size();
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer());
+        gl::Viewport(0, 0, width as GLsizei, height as GLsizei);
+    }
+}
+
+#[test]
+fn draw_matches_golden_image() {
+    let headless = Headless::new();
+    let context = GlowContext::new(glow_context(&headless));
+    assert_eq!(context.api(), Api::Gl);
+    let texture = common::upload(TextureTarget::Rectangle, 8, 8, &common::gradient());
+    let target = common::render_target(&headless.gl, 32, 24);
+    bind(&target);
+    context.draw_with_options(glow_texture(texture), &DrawOptions {
+        orientation: Orientation { flip_y: true, ..Orientation::default() },
+        ..DrawOptions::default()
+    });
+    common::assert_golden("gradient", &target);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn gles_draws_2d_textures_with_options() {
+    let headless = Headless::gles();
+    let context = GlowContext::new(glow_context(&headless));
+    assert_eq!(context.api(), Api::Gles);
+    let texture = common::upload(TextureTarget::Texture2D, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+    bind(&target);
+    context.draw_with_options(glow_texture(texture), &DrawOptions {
+        target: TextureTarget::Texture2D,
+        dest: Rect::pixels(0.0, 0.0, 2.0, 2.0),
+        orientation: Orientation { flip_y: true, ..Orientation::default() },
+        background: Some([0.0, 0.0, 0.0, 1.0]),
+        ..DrawOptions::default()
+    });
+
+    // The target is bottom-up, so the quad lands in its bottom left corner.
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 2), RED);
+    assert_eq!(common::pixel(&pixels, 4, 1, 2), GREEN);
+    assert_eq!(common::pixel(&pixels, 4, 0, 3), BLUE);
+    assert_eq!(common::pixel(&pixels, 4, 1, 3), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), BLACK);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn blending_is_restored() {
+    let headless = Headless::new();
+    let context = GlowContext::new(glow_context(&headless));
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &[0, 0, 255, 255]);
+    let target = common::render_target(&headless.gl, 1, 1);
+    bind(&target);
+    unsafe {
+        gl::ClearColor(1.0, 0.0, 0.0, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    }
+    context.draw_with_options(glow_texture(texture), &DrawOptions {
+        blend: Some(BlendMode::Additive),
+        ..DrawOptions::default()
+    });
+
+    assert_eq!(common::pixel(&target.read_pixels(), 1, 0, 0), [255, 0, 255, 255]);
+    assert_eq!(unsafe { gl::IsEnabled(gl::BLEND) }, gl::FALSE);
+    common::assert_no_gl_error();
+}
@@ -0,0 +1,67 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for the `image` crate integration.
+
+#![cfg(all(feature = "image", target_os = "linux"))]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLUE, GREEN, Headless, RED, WHITE};
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use lord_drawquaad::{Context, Texture, TextureFormat};
+
+#[test]
+fn images_round_trip_through_render_targets() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let source = RgbaImage::from_raw(8, 8, common::gradient()).unwrap();
+    let texture = Texture::from_image(&headless.gl, &DynamicImage::ImageRgba8(source.clone()));
+    assert_eq!(texture.format(), TextureFormat::Rgba8);
+
+    let target = common::render_target(&headless.gl, 8, 8);
+    context.render_to(&target, || context.draw_texture(&texture));
+    let output = target.read_image();
+    assert_eq!(output.dimensions(), (8, 8));
+    assert_eq!(output.into_raw(), source.into_raw());
+}
+
+#[test]
+fn rgb_and_gray_images_draw_opaque() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let target = common::render_target(&headless.gl, 2, 1);
+
+    let rgb = RgbImage::from_raw(2, 1, vec![255, 0, 0, 0, 0, 255]).unwrap();
+    let texture = Texture::from_image(&headless.gl, &DynamicImage::ImageRgb8(rgb));
+    assert_eq!(texture.format(), TextureFormat::Rgb8);
+    context.render_to(&target, || context.draw_texture(&texture));
+    let output = target.read_image();
+    assert_eq!(output.get_pixel(0, 0).data, RED);
+    assert_eq!(output.get_pixel(1, 0).data, BLUE);
+
+    let gray = GrayImage::from_raw(2, 1, vec![255, 0]).unwrap();
+    let texture = Texture::from_image(&headless.gl, &DynamicImage::ImageLuma8(gray));
+    context.render_to(&target, || context.draw_texture(&texture));
+    let output = target.read_image();
+    assert_eq!(output.get_pixel(0, 0).data, WHITE);
+    assert_eq!(output.get_pixel(1, 0).data, [0, 0, 0, 255]);
+}
+
+#[test]
+fn rgba_images_upload_directly() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let image = RgbaImage::from_raw(2, 2, common::corners()).unwrap();
+    let texture = Texture::from_rgba_image(&headless.gl, &image);
+    assert_eq!(texture.size(), (2, 2));
+
+    let target = common::render_target(&headless.gl, 2, 2);
+    context.render_to(&target, || context.draw_texture(&texture));
+    assert_eq!(target.read_image().get_pixel(1, 0).data, GREEN);
+}
@@ -0,0 +1,278 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for drawing under OpenGL 2.1, with GLSL 1.20 shaders and no vertex array objects.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLACK, BLUE, GREEN, Headless, RED, WHITE};
+use gl::types::{GLenum, GLint, GLuint};
+use lord_drawquaad::{Api, Batch, Context, ContextOptions, DrawOptions, Filter, Profile, Rect};
+use lord_drawquaad::{TextureTarget, YuvOptions, YuvPlanes};
+use std::os::raw::c_void;
+use std::ptr;
+
+#[test]
+fn api_is_detected() {
+    let headless = Headless::legacy();
+    assert_eq!(Api::current(&headless.gl), Api::LegacyGl);
+    let context = Context::new(&headless.gl);
+    assert_eq!(context.api(), Api::LegacyGl);
+
+    let capabilities = context.capabilities();
+    assert_eq!(capabilities.profile, Profile::Compatibility);
+    assert_eq!(capabilities.version, (2, 1));
+    assert!(capabilities.max_texture_units >= 2);
+    assert!(capabilities.rectangle_textures);
+    assert!(capabilities.has_extension("GL_ARB_texture_rectangle"));
+}
+
+#[test]
+fn draw_matches_golden_images() {
+    let headless = Headless::legacy();
+    let context = Context::new(&headless.gl);
+    for &texture_target in &[TextureTarget::Rectangle, TextureTarget::Texture2D] {
+        let texture = common::upload(texture_target, 8, 8, &common::gradient());
+        let target = common::render_target(&headless.gl, 32, 24);
+        context.render_to(&target, || context.draw_with_target(texture, texture_target));
+        common::assert_golden("gradient", &target);
+
+        let target = common::render_target(&headless.gl, 32, 32);
+        let options = DrawOptions {
+            target: texture_target,
+            filter: Filter::Lanczos3,
+            ..DrawOptions::default()
+        };
+        context.draw_to(&target, texture, &options);
+        common::assert_golden("filter_lanczos3", &target);
+
+        let target = common::render_target(&headless.gl, 3, 3);
+        let options = DrawOptions {
+            target: texture_target,
+            filter: Filter::Area,
+            ..DrawOptions::default()
+        };
+        context.draw_to(&target, texture, &options);
+        common::assert_golden("filter_area", &target);
+    }
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn regions_and_batches_work() {
+    let headless = Headless::legacy();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+    context.render_to(&target, || {
+        context.draw_region(texture,
+                            Rect::pixels(1.0, 0.0, 1.0, 1.0),
+                            Rect::pixels(0.0, 0.0, 2.0, 2.0))
+    });
+    assert_eq!(common::pixel(&target.read_pixels(), 4, 1, 1), GREEN);
+
+    let mut batch = Batch::new();
+    batch.add(texture, Rect::pixels(0.0, 1.0, 1.0, 1.0), Rect::pixels(2.0, 2.0, 2.0, 2.0))
+         .add(texture, Rect::normalized(0.0, 0.0, 1.0, 1.0), Rect::pixels(0.0, 2.0, 2.0, 2.0));
+    context.render_to(&target, || context.draw_batch(&batch));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), GREEN);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), BLACK);
+    assert_eq!(common::pixel(&pixels, 4, 0, 2), RED);
+    assert_eq!(common::pixel(&pixels, 4, 1, 3), WHITE);
+    assert_eq!(common::pixel(&pixels, 4, 3, 3), BLUE);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn preserve_state_restores_bindings() {
+    let headless = Headless::legacy();
+    let options = ContextOptions { preserve_state: true, ..ContextOptions::default() };
+    let context = Context::with_options(&headless.gl, &options).unwrap();
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+
+    // Legacy GL has no vertex array objects of our own to hide behind, so the caller's
+    // attribute pointers must survive the draws.
+    let mut buffer = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut buffer);
+        gl::BindBuffer(gl::ARRAY_BUFFER, buffer);
+        gl::BufferData(gl::ARRAY_BUFFER, 64, ptr::null(), gl::STATIC_DRAW);
+        gl::VertexAttribPointer(0, 3, gl::UNSIGNED_BYTE, gl::TRUE, 12, 4 as *const c_void);
+        gl::EnableVertexAttribArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+    context.render_to(&target, || {
+        context.draw(texture);
+        let mut batch = Batch::new();
+        batch.add(texture, Rect::normalized(0.0, 0.0, 1.0, 1.0), Rect::pixels(0.0, 0.0, 2.0, 2.0));
+        context.draw_batch(&batch);
+    });
+    assert_eq!(common::pixel(&target.read_pixels(), 4, 3, 3), WHITE);
+
+    let get = |parameter| {
+        let mut value = 0;
+        unsafe {
+            gl::GetIntegerv(parameter, &mut value);
+        }
+        value
+    };
+    let get_attribute = |index, parameter| {
+        let mut value = 0;
+        unsafe {
+            gl::GetVertexAttribiv(index, parameter, &mut value);
+        }
+        value
+    };
+    let mut pointer = ptr::null_mut();
+    unsafe {
+        gl::GetVertexAttribPointerv(0, gl::VERTEX_ATTRIB_ARRAY_POINTER, &mut pointer);
+    }
+    assert_eq!(get(gl::CURRENT_PROGRAM), 0);
+    assert_eq!(get(gl::ARRAY_BUFFER_BINDING), 0);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_ENABLED), 1);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING) as GLuint, buffer);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_SIZE), 3);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_TYPE) as GLenum, gl::UNSIGNED_BYTE);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_NORMALIZED), gl::TRUE as GLint);
+    assert_eq!(get_attribute(0, gl::VERTEX_ATTRIB_ARRAY_STRIDE), 12);
+    assert_eq!(pointer as usize, 4);
+    assert_eq!(get_attribute(1, gl::VERTEX_ATTRIB_ARRAY_ENABLED), 0);
+    assert_eq!(get_attribute(1, gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING), 0);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn custom_shaders_and_yuv_work() {
+    let headless = Headless::legacy();
+    let mut context = Context::with_options(&headless.gl, &ContextOptions {
+        fragment_shader: Some(r#"
+uniform SAMPLER uMask;
+uniform vec2 uMaskSize;
+uniform float uScale;
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    vec4 mask = sampleAt(uMask, uMaskSize, vTexCoord);
+    oFragColor = vec4(sampleTexture(vTexCoord).bgr * mask.a * uScale, 1.0);
+}
+"#.to_owned()),
+        preserve_state: true,
+        ..ContextOptions::default()
+    }).unwrap();
+    context.set_uniform("uScale", 2.0f32).unwrap();
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &RED);
+    let mask = common::upload(TextureTarget::Rectangle, 2, 1, &[0, 0, 0, 0, 0, 0, 0, 128]);
+    let target = common::render_target(&headless.gl, 2, 1);
+    context.render_to(&target, || {
+        context.draw_multi(&[
+            ("uTexture", texture, TextureTarget::Rectangle),
+            ("uMask", mask, TextureTarget::Rectangle),
+        ])
+    });
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 2, 0, 0), BLACK);
+    assert_eq!(common::pixel(&pixels, 2, 1, 0), BLUE);
+
+    // Chroma planes are smaller than the luma plane, so each needs its own size.
+    let context = Context::new(&headless.gl);
+    let y = common::upload_with_format(TextureTarget::Rectangle,
+                                       2,
+                                       1,
+                                       gl::R8,
+                                       gl::RED,
+                                       &[235, 16]);
+    let u = common::upload_with_format(TextureTarget::Rectangle, 1, 1, gl::R8, gl::RED, &[128]);
+    let v = common::upload_with_format(TextureTarget::Rectangle, 1, 1, gl::R8, gl::RED, &[128]);
+    context.render_to(&target, || {
+        context.draw_yuv(YuvPlanes::I420 { y: y, u: u, v: v }, &YuvOptions::default())
+    });
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 2, 0, 0), WHITE);
+    assert_eq!(common::pixel(&pixels, 2, 1, 0), BLACK);
+    common::assert_no_gl_error();
+}
+
+// GLSL 1.20 has no `#` or `##` preprocessor operators. Mesa accepts them anyway, so drawing alone
+// can't catch them; inspect the sources that were compiled instead.
+#[test]
+fn shaders_avoid_preprocessor_operators() {
+    let headless = Headless::legacy();
+    let context = Context::new(&headless.gl);
+    let target = common::render_target(&headless.gl, 1, 1);
+    for &texture_target in &[TextureTarget::Rectangle, TextureTarget::Texture2D] {
+        let texture = common::upload(texture_target, 1, 1, &RED);
+        let planes = YuvPlanes::I420 { y: texture, u: texture, v: texture };
+        let options = DrawOptions {
+            target: texture_target,
+            filter: Filter::Area,
+            ..DrawOptions::default()
+        };
+        context.render_to(&target, || {
+            context.draw_with_options(texture, &options);
+            assert_no_preprocessor_operators(&current_shader_sources());
+            context.draw_yuv_with_options(planes, &YuvOptions::default(), &options);
+            assert_no_preprocessor_operators(&current_shader_sources());
+        });
+    }
+    common::assert_no_gl_error();
+}
+
+fn current_shader_sources() -> Vec<String> {
+    unsafe {
+        let mut program = 0;
+        gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program);
+        let mut shaders = [0; 2];
+        let mut count = 0;
+        gl::GetAttachedShaders(program as GLuint, 2, &mut count, shaders.as_mut_ptr());
+        assert_eq!(count, 2);
+        shaders.iter().map(|&shader| {
+            let mut length = 0;
+            gl::GetShaderiv(shader, gl::SHADER_SOURCE_LENGTH, &mut length);
+            let mut source = vec![0u8; length as usize];
+            let mut written = 0;
+            gl::GetShaderSource(shader, length, &mut written, source.as_mut_ptr() as *mut _);
+            source.truncate(written as usize);
+            String::from_utf8(source).unwrap()
+        }).collect()
+    }
+}
+
+fn assert_no_preprocessor_operators(sources: &[String]) {
+    for source in sources {
+        for line in source.lines() {
+            let line = line.trim();
+            if line.starts_with("#define") {
+                assert!(!line[1..].contains('#'), "preprocessor operator in `{}`", line);
+            }
+        }
+    }
+}
+
+#[test]
+fn compile_errors_report_the_offending_line() {
+    let headless = Headless::legacy();
+    let result = Context::with_fragment_shader(&headless.gl, r#"
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = bogus(vTexCoord);
+}
+"#);
+    match result {
+        Err(lord_drawquaad::Error::ShaderCompilation { source_line, .. }) => {
+            assert!(source_line.unwrap().contains("bogus"))
+        }
+        Err(error) => panic!("unexpected error: {}", error),
+        Ok(_) => panic!("compiling an invalid shader succeeded"),
+    }
+}
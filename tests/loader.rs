@@ -0,0 +1,49 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for calling GL through function tables rather than global bindings.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLUE, Headless, RED};
+use lord_drawquaad::{Context, Texture, TextureFormat, TextureOptions, TextureTarget};
+
+#[test]
+fn contexts_in_different_gl_contexts_coexist() {
+    let es = Headless::gles();
+    let es_context = Context::new(&es.gl);
+    let options = TextureOptions {
+        target: Some(TextureTarget::Texture2D),
+        ..TextureOptions::default()
+    };
+    let es_texture = Texture::with_options(&es.gl, 1, 1, TextureFormat::Rgba8, &BLUE, &options);
+    let es_target = common::render_target(&es.gl, 1, 1);
+
+    let desktop = Headless::new();
+    let desktop_context = Context::new(&desktop.gl);
+    let desktop_texture = Texture::from_rgba8(&desktop.gl, 1, 1, &RED);
+    let desktop_target = common::render_target(&desktop.gl, 1, 1);
+    desktop_context.render_to(&desktop_target,
+                              || desktop_context.draw_texture(&desktop_texture));
+
+    es.make_current();
+    es_context.render_to(&es_target, || es_context.draw_texture(&es_texture));
+    assert_eq!(common::pixel(&es_target.read_pixels(), 1, 0, 0), BLUE);
+    common::assert_no_gl_error();
+
+    desktop.make_current();
+    assert_eq!(common::pixel(&desktop_target.read_pixels(), 1, 0, 0), RED);
+    common::assert_no_gl_error();
+
+    // Delete each context's objects while it's current.
+    drop((desktop_context, desktop_texture, desktop_target));
+    drop(desktop);
+    es.make_current();
+}
@@ -0,0 +1,135 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for rendering into and reading back from render targets.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLUE, GREEN, Headless, RED, WHITE};
+use gl::types::GLint;
+use lord_drawquaad::{Context, DrawOptions, RenderTarget, TextureTarget};
+use std::panic::{self, AssertUnwindSafe};
+
+#[test]
+fn read_pixels_is_top_down() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = RenderTarget::new(&headless.gl, 2, 2, TextureTarget::Rectangle).unwrap();
+    context.render_to(&target, || context.draw(texture));
+
+    assert_eq!(target.size(), (2, 2));
+    assert_eq!(target.read_pixels(), common::corners());
+}
+
+#[test]
+fn render_to_restores_framebuffer_and_viewport() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let outer = common::render_target(&headless.gl, 8, 8);
+    let inner = common::render_target(&headless.gl, 2, 2);
+    let get = |parameter| {
+        let mut values = [0; 4];
+        unsafe {
+            gl::GetIntegerv(parameter, values.as_mut_ptr());
+        }
+        values
+    };
+
+    unsafe {
+        gl::Viewport(1, 2, 3, 4);
+    }
+    context.render_to(&outer, || {
+        assert_eq!(get(gl::VIEWPORT), [0, 0, 8, 8]);
+        context.render_to(&inner, || {
+            assert_eq!(get(gl::FRAMEBUFFER_BINDING)[0], inner.framebuffer() as GLint);
+            assert_eq!(get(gl::VIEWPORT), [0, 0, 2, 2]);
+        });
+        assert_eq!(get(gl::FRAMEBUFFER_BINDING)[0], outer.framebuffer() as GLint);
+        assert_eq!(get(gl::VIEWPORT), [0, 0, 8, 8]);
+    });
+    assert_eq!(get(gl::FRAMEBUFFER_BINDING)[0], 0);
+    assert_eq!(get(gl::VIEWPORT), [1, 2, 3, 4]);
+}
+
+#[test]
+fn render_to_restores_state_when_drawing_panics() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let outer = common::render_target(&headless.gl, 2, 2);
+    let inner = common::render_target(&headless.gl, 4, 4);
+
+    unsafe {
+        gl::Viewport(0, 0, 2, 2);
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        context.render_to(&inner, || panic!("drawing failed"))
+    }));
+    assert!(result.is_err());
+    let mut framebuffer = 0;
+    let mut viewport = [0; 4];
+    unsafe {
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+    }
+    assert_eq!(framebuffer, 0);
+    assert_eq!(viewport, [0, 0, 2, 2]);
+
+    // Outside `render_to()`, drawing isn't flipped, so a framebuffer bound by hand reads back
+    // bottom-up.
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, outer.framebuffer());
+    }
+    context.draw(texture);
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+    let pixels = outer.read_pixels();
+    assert_eq!(common::pixel(&pixels, 2, 0, 0), BLUE);
+    assert_eq!(common::pixel(&pixels, 2, 0, 1), RED);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn chained_passes_keep_orientation() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+
+    // Each pass reads the previous pass's texture, alternating between texture types.
+    let first = RenderTarget::new(&headless.gl, 2, 2, TextureTarget::Texture2D).unwrap();
+    let second = RenderTarget::new(&headless.gl, 4, 4, TextureTarget::Rectangle).unwrap();
+    let third = common::render_target(&headless.gl, 4, 4);
+    context.render_to(&first, || context.draw(texture));
+    for &(source, destination) in &[(&first, &second), (&second, &third)] {
+        let options = DrawOptions { target: source.target(), ..DrawOptions::default() };
+        context.draw_to(destination, source.texture(), &options);
+    }
+
+    let pixels = third.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 0, 0), RED);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), GREEN);
+    assert_eq!(common::pixel(&pixels, 4, 0, 3), BLUE);
+    assert_eq!(common::pixel(&pixels, 4, 3, 3), WHITE);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn floating_point_targets_are_complete() {
+    let headless = Headless::new();
+    let target = RenderTarget::with_format(&headless.gl,
+                                           4,
+                                           4,
+                                           TextureTarget::Texture2D,
+                                           gl::RGBA16F).unwrap();
+    assert_eq!(target.target(), TextureTarget::Texture2D);
+    common::assert_no_gl_error();
+}
@@ -0,0 +1,183 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for custom fragment shaders, uniforms, multiple textures, and YUV conversion.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLACK, BLUE, Headless, WHITE};
+use lord_drawquaad::{Context, DrawOptions, Error, ShaderStage, TextureTarget, YuvOptions};
+use lord_drawquaad::YuvPlanes;
+
+#[test]
+fn custom_fragment_shader_uses_prelude() {
+    let headless = Headless::new();
+    let context = Context::with_fragment_shader(&headless.gl, r#"
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = sampleTexture(vTexCoord).bgra;
+}
+"#).unwrap();
+    let texture = common::upload(TextureTarget::Rectangle, 2, 2, &common::corners());
+    let target = common::render_target(&headless.gl, 4, 4);
+    context.render_to(&target, || context.draw(texture));
+
+    assert_eq!(common::pixel(&target.read_pixels(), 4, 0, 0), BLUE);
+}
+
+#[test]
+fn compile_errors_report_the_offending_line() {
+    let headless = Headless::new();
+    let result = Context::with_fragment_shader(&headless.gl, r#"
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = bogus(vTexCoord);
+}
+"#);
+    match result {
+        Err(Error::ShaderCompilation { stage: ShaderStage::Fragment, source_line, .. }) => {
+            assert!(source_line.unwrap().contains("bogus"))
+        }
+        Err(error) => panic!("unexpected error: {}", error),
+        Ok(_) => panic!("compiling an invalid shader succeeded"),
+    }
+}
+
+#[test]
+fn compile_errors_skip_warnings() {
+    let headless = Headless::new();
+    let result = Context::with_fragment_shader(&headless.gl, r#"
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    float uninitialized;
+    vec4 color = vec4(uninitialized);
+    oFragColor = bogus(vTexCoord) * color;
+}
+"#);
+    match result {
+        Err(Error::ShaderCompilation { log, source_line, .. }) => {
+            assert!(log.lines().next().unwrap().contains("warning"), "{}", log);
+            assert!(source_line.unwrap().contains("bogus"))
+        }
+        Err(error) => panic!("unexpected error: {}", error),
+        Ok(_) => panic!("compiling an invalid shader succeeded"),
+    }
+}
+
+#[test]
+fn uniforms_are_validated_and_applied() {
+    let headless = Headless::new();
+    let mut context = Context::with_fragment_shader(&headless.gl, r#"
+uniform float uScale;
+uniform vec4 uTint;
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = sampleTexture(vTexCoord) * uScale * uTint;
+}
+"#).unwrap();
+    context.set_uniform("uScale", 0.5f32).unwrap();
+    context.set_uniform("uTint", [1.0f32, 1.0, 1.0, 2.0]).unwrap();
+    match context.set_uniform("uScale", 1i32) {
+        Err(Error::UniformTypeMismatch { ref name }) => assert_eq!(name, "uScale"),
+        other => panic!("expected a type mismatch, got {:?}", other.err()),
+    }
+    match context.set_uniform("uMissing", 1.0f32) {
+        Err(Error::UnknownUniform { ref name }) => assert_eq!(name, "uMissing"),
+        other => panic!("expected an unknown uniform, got {:?}", other.err()),
+    }
+
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &WHITE);
+    let target = common::render_target(&headless.gl, 1, 1);
+    context.render_to(&target, || context.draw(texture));
+
+    let pixel = common::pixel(&target.read_pixels(), 1, 0, 0);
+    assert!(pixel[0] >= 127 && pixel[0] <= 128 && pixel[3] == 255, "{:?}", pixel);
+}
+
+#[test]
+fn draw_multi_binds_every_texture() {
+    let headless = Headless::new();
+    let context = Context::with_fragment_shader(&headless.gl, r#"
+uniform sampler2D uMask;
+in vec2 vTexCoord;
+out vec4 oFragColor;
+void main() {
+    oFragColor = vec4(sampleTexture(vTexCoord).rgb * texture(uMask, vTexCoord).a, 1.0);
+}
+"#).unwrap();
+    let texture = common::upload(TextureTarget::Rectangle, 1, 1, &WHITE);
+    let mask = common::upload(TextureTarget::Texture2D, 1, 1, &[0, 0, 0, 128]);
+    let target = common::render_target(&headless.gl, 1, 1);
+    context.render_to(&target, || {
+        context.draw_multi(&[
+            ("uTexture", texture, TextureTarget::Rectangle),
+            ("uMask", mask, TextureTarget::Texture2D),
+        ])
+    });
+
+    let pixel = common::pixel(&target.read_pixels(), 1, 0, 0);
+    assert!(pixel[0] >= 127 && pixel[0] <= 129, "{:?}", pixel);
+    common::assert_no_gl_error();
+}
+
+#[test]
+#[should_panic(expected = "textures can be drawn at once")]
+fn draw_multi_rejects_too_many_textures() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let count = context.capabilities().max_texture_units + 1;
+    let textures: Vec<_> = (0..count).map(|_| ("uTexture", 0, TextureTarget::Rectangle)).collect();
+    context.draw_multi(&textures);
+}
+
+#[test]
+fn yuv_planes_convert_to_rgb() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let target = common::render_target(&headless.gl, 2, 2);
+
+    // Limited-range white and black, with neutral chroma.
+    let y = common::upload_with_format(TextureTarget::Rectangle,
+                                       2,
+                                       2,
+                                       gl::R8,
+                                       gl::RED,
+                                       &[235, 16, 16, 235]);
+    let u = common::upload_with_format(TextureTarget::Rectangle, 1, 1, gl::R8, gl::RED, &[128]);
+    let v = common::upload_with_format(TextureTarget::Rectangle, 1, 1, gl::R8, gl::RED, &[128]);
+    context.render_to(&target, || {
+        context.draw_yuv(YuvPlanes::I420 { y: y, u: u, v: v }, &YuvOptions::default())
+    });
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 2, 0, 0), WHITE);
+    assert_eq!(common::pixel(&pixels, 2, 1, 0), BLACK);
+
+    // BT.709 red.
+    let y = common::upload_with_format(TextureTarget::Texture2D, 1, 1, gl::R8, gl::RED, &[63]);
+    let uv = common::upload_with_format(TextureTarget::Texture2D,
+                                        1,
+                                        1,
+                                        gl::RG8,
+                                        gl::RG,
+                                        &[102, 240]);
+    let options = DrawOptions { target: TextureTarget::Texture2D, ..DrawOptions::default() };
+    context.render_to(&target, || {
+        context.draw_yuv_with_options(YuvPlanes::Nv12 { y: y, uv: uv },
+                                      &YuvOptions::default(),
+                                      &options)
+    });
+    let pixel = common::pixel(&target.read_pixels(), 2, 0, 0);
+    assert!(pixel[0] > 250 && pixel[1] < 5 && pixel[2] < 5, "{:?}", pixel);
+    common::assert_no_gl_error();
+}
@@ -0,0 +1,154 @@
+/* Any copyright is dedicated to the Public Domain.
+ * http://creativecommons.org/publicdomain/zero/1.0/ */
+
+//! Tests for uploading textures from CPU pixel buffers.
+
+#![cfg(target_os = "linux")]
+
+extern crate gl;
+extern crate image;
+extern crate khronos_egl as egl;
+extern crate lord_drawquaad;
+
+mod common;
+
+use common::{BLUE, GREEN, Headless, RED, WHITE};
+use lord_drawquaad::{Context, DrawOptions, Rect, Texture, TextureFilter, TextureFormat};
+use lord_drawquaad::{TextureOptions, TextureTarget, TextureWrap};
+
+fn draw_pixel(context: &Context, texture: &Texture) -> [u8; 4] {
+    let target = common::render_target(context.gl(), 1, 1);
+    context.render_to(&target, || context.draw_texture(texture));
+    common::pixel(&target.read_pixels(), 1, 0, 0)
+}
+
+#[test]
+fn formats_upload_and_draw() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let cases: [(TextureFormat, &[u8], [u8; 4]); 6] = [
+        (TextureFormat::Rgba8, &[255, 0, 0, 255], RED),
+        (TextureFormat::Bgra8, &[255, 0, 0, 255], BLUE),
+        (TextureFormat::Rgb8, &[0, 255, 0], GREEN),
+        (TextureFormat::R8, &[255], RED),
+        (TextureFormat::Rg8, &[255, 255], [255, 255, 0, 255]),
+        // 1.0, 0.5, 0.0, and 1.0 as little-endian half floats.
+        (TextureFormat::Rgba16F,
+         &[0x00, 0x3c, 0x00, 0x38, 0x00, 0x00, 0x00, 0x3c],
+         [255, 128, 0, 255]),
+    ];
+    for &(format, pixels, expected) in &cases {
+        let texture = Texture::new(&headless.gl, 1, 1, format, pixels);
+        assert_eq!(texture.format(), format);
+        assert_eq!(draw_pixel(&context, &texture), expected, "{:?}", format);
+    }
+
+    let texture = Texture::from_rgba_f32(&headless.gl, 1, 1, &[0.0, 0.25, 1.0, 1.0]);
+    assert_eq!(texture.format(), TextureFormat::Rgba16F);
+    assert_eq!(draw_pixel(&context, &texture), [0, 64, 255, 255]);
+    common::assert_no_gl_error();
+}
+
+#[test]
+fn odd_widths_are_tightly_packed() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let pixels = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 0];
+    let options = TextureOptions {
+        filter: TextureFilter::Nearest,
+        ..TextureOptions::default()
+    };
+    let texture = Texture::with_options(&headless.gl, 3, 2, TextureFormat::Rgb8, &pixels, &options);
+    let target = common::render_target(&headless.gl, 3, 2);
+    context.render_to(&target, || context.draw_texture(&texture));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 3, 2, 0), BLUE);
+    assert_eq!(common::pixel(&pixels, 3, 0, 1), WHITE);
+    assert_eq!(common::pixel(&pixels, 3, 2, 1), GREEN);
+}
+
+#[test]
+fn update_replaces_a_region() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let texture = Texture::from_rgba8(&headless.gl, 2, 2, &common::corners());
+    texture.set_filter(TextureFilter::Nearest);
+    texture.update(1, 1, 1, 1, &RED);
+    let target = common::render_target(&headless.gl, 2, 2);
+    context.render_to(&target, || context.draw_texture(&texture));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 2, 1, 0), GREEN);
+    assert_eq!(common::pixel(&pixels, 2, 0, 1), BLUE);
+    assert_eq!(common::pixel(&pixels, 2, 1, 1), RED);
+}
+
+#[test]
+#[should_panic(expected = "region doesn't fit")]
+fn update_rejects_regions_that_wrap_around() {
+    let headless = Headless::new();
+    let texture = Texture::from_rgba8(&headless.gl, 2, 2, &common::corners());
+    texture.update(u32::max_value(), 0, 2, 1, &[0; 8]);
+}
+
+#[test]
+fn filter_controls_interpolation() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let pixels: Vec<u8> = RED.iter().chain(GREEN.iter()).cloned().collect();
+    let texture = Texture::from_rgba8(&headless.gl, 2, 1, &pixels);
+    let target = common::render_target(&headless.gl, 4, 1);
+
+    texture.set_filter(TextureFilter::Nearest);
+    context.render_to(&target, || context.draw_texture(&texture));
+    assert_eq!(common::pixel(&target.read_pixels(), 4, 1, 0), RED);
+
+    texture.set_filter(TextureFilter::Linear);
+    context.render_to(&target, || context.draw_texture(&texture));
+    let pixel = common::pixel(&target.read_pixels(), 4, 1, 0);
+    assert!(pixel[0] > 150 && pixel[0] < 210 && pixel[1] > 50 && pixel[1] < 100, "{:?}", pixel);
+}
+
+#[test]
+fn wrap_repeats_2d_textures() {
+    let headless = Headless::new();
+    let context = Context::new(&headless.gl);
+    let pixels: Vec<u8> = RED.iter().chain(GREEN.iter()).cloned().collect();
+    let options = TextureOptions {
+        target: Some(TextureTarget::Texture2D),
+        filter: TextureFilter::Nearest,
+        wrap: TextureWrap::Repeat,
+    };
+    let texture = Texture::with_options(&headless.gl,
+                                        2,
+                                        1,
+                                        TextureFormat::Rgba8,
+                                        &pixels,
+                                        &options);
+    assert_eq!(texture.target(), TextureTarget::Texture2D);
+    let target = common::render_target(&headless.gl, 4, 1);
+    let options = DrawOptions {
+        src: Rect::normalized(0.0, 0.0, 2.0, 1.0),
+        ..DrawOptions::default()
+    };
+    context.render_to(&target, || context.draw_texture_with_options(&texture, &options));
+
+    let pixels = target.read_pixels();
+    assert_eq!(common::pixel(&pixels, 4, 2, 0), RED);
+    assert_eq!(common::pixel(&pixels, 4, 3, 0), GREEN);
+
+    texture.set_wrap(TextureWrap::ClampToEdge);
+    context.render_to(&target, || context.draw_texture_with_options(&texture, &options));
+    assert_eq!(common::pixel(&target.read_pixels(), 4, 2, 0), GREEN);
+}
+
+#[test]
+fn drop_deletes_the_texture() {
+    let headless = Headless::new();
+    let texture = Texture::from_rgba8(&headless.gl, 1, 1, &WHITE);
+    let id = texture.id();
+    assert_eq!(unsafe { gl::IsTexture(id) }, gl::TRUE);
+    drop(texture);
+    assert_eq!(unsafe { gl::IsTexture(id) }, gl::FALSE);
+}